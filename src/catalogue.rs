@@ -0,0 +1,550 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs,
+    io,
+    iter::Sum,
+    ops::{
+        Add,
+        Index,
+        IndexMut,
+        Mul,
+    },
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use toml::Spanned;
+
+use crate::material::Material;
+
+/// The catalogue which is compiled into the binary.
+const DEFAULT_CATALOGUE: &str = include_str!("../data/catalogue.toml");
+/// Where a user-supplied catalogue is looked for, relative to the working directory.
+const CATALOGUE_PATH: &str = "catalogue.toml";
+/// The catalogue format version this build understands.
+const CATALOGUE_VERSION: u32 = 2;
+/// The most of a material a crate can cost. Real costs are in the hundreds, so anything above
+/// this is a typo, and would overflow when working out queue costs.
+const MAX_CRATE_COST: u32 = 1_000_000;
+/// The most units a crate can hold. Real crates hold a handful of vehicles at most, so anything
+/// above this is a typo, and would overflow when working out how many units queues make.
+const MAX_UNITS_PER_CRATE: u32 = 1_000;
+
+pub struct Catalogue {
+    pub categories: Vec<Category>,
+    pub recipes: Vec<Recipe>,
+}
+
+pub struct Category {
+    pub name: String,
+    pub items: Vec<Item>,
+}
+
+pub struct Item {
+    /// Identifies the item in saved lists and profiles. Unlike the name, this stays the same
+    /// when a game update renames the item.
+    pub id: String,
+    pub name: String,
+    pub short_name: Option<String>,
+    /// What a single crate costs at a regular factory.
+    pub crate_cost: Cost,
+    /// How many crates the MPF produces for one queue of this item.
+    pub crates_per_queue: u32,
+    /// How many of the item come in one crate. This is more than one for vehicles and
+    /// shippables, which the MPF packs several to a crate.
+    pub units_per_crate: u32,
+    /// What one full queue costs at the MPF, with the bulk discounts applied.
+    pub queue_cost: Cost,
+    pub useless: bool,
+    pub faction: Option<Faction>,
+    /// The index of the item's category in `Catalogue::categories`.
+    pub category: usize,
+    /// Where the item is listed within its category.
+    pub position: usize,
+}
+
+/// An amount of every material, indexed by `Material`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cost([u32; Material::ALL.len()]);
+
+/// What the refinery turns into one of a material.
+pub struct Recipe {
+    pub material: Material,
+    /// The raw resources it takes, by name.
+    pub inputs: Vec<(String, u32)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Faction {
+    Warden,
+    Colonial,
+}
+
+impl Item {
+    /// The short name if the item has one, and the full name otherwise.
+    pub fn display_name(&self) -> &str {
+        self.short_name.as_deref().unwrap_or(&self.name)
+    }
+
+    /// How many of the item one queue at the MPF produces.
+    pub fn units_per_queue(&self) -> u32 {
+        self.crates_per_queue * self.units_per_crate
+    }
+}
+
+impl Cost {
+    pub fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Lists the materials the cost includes, in the order of `Material::ALL`.
+    pub fn iter(&self) -> impl Iterator<Item = (Material, u32)> + '_ {
+        Material::ALL
+            .into_iter()
+            .map(|material| (material, self[material]))
+            .filter(|(_, amount)| *amount > 0)
+    }
+
+    /// Applies a percentage discount, rounding down like the MPF does.
+    fn discounted(mut self, percent: u32) -> Self {
+        for amount in &mut self.0 {
+            *amount = *amount * (100 - percent) / 100;
+        }
+        self
+    }
+}
+
+impl Index<Material> for Cost {
+    type Output = u32;
+
+    fn index(&self, material: Material) -> &u32 {
+        &self.0[material.index()]
+    }
+}
+
+impl IndexMut<Material> for Cost {
+    fn index_mut(&mut self, material: Material) -> &mut u32 {
+        &mut self.0[material.index()]
+    }
+}
+
+impl Add for Cost {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self {
+        for (amount, other) in self.0.iter_mut().zip(rhs.0) {
+            *amount = amount.saturating_add(other);
+        }
+        self
+    }
+}
+
+impl Sum for Cost {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+impl Mul<u32> for Cost {
+    type Output = Self;
+
+    fn mul(mut self, rhs: u32) -> Self {
+        for amount in &mut self.0 {
+            *amount = amount.saturating_mul(rhs);
+        }
+        self
+    }
+}
+
+impl Catalogue {
+    /// Loads `catalogue.toml` from the working directory if there is one, and falls back to the
+    /// built-in catalogue otherwise.
+    pub fn load() -> Result<Self, CatalogueError> {
+        match fs::read_to_string(CATALOGUE_PATH) {
+            Ok(source) => Self::parse(&source, CATALOGUE_PATH),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::builtin()),
+            Err(err) => Err(CatalogueError::Io {
+                path: CATALOGUE_PATH.to_string(),
+                err,
+            }),
+        }
+    }
+
+    pub fn builtin() -> Self {
+        Self::parse(DEFAULT_CATALOGUE, "<built-in catalogue>")
+            .expect("the built-in catalogue should always be valid")
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &Item> {
+        self.categories.iter().flat_map(|category| &category.items)
+    }
+
+    pub fn item(&self, id: &str) -> Option<&Item> {
+        self.items().find(|item| item.id == id)
+    }
+
+    pub fn find_item(&self, name: &str) -> Option<&Item> {
+        self.items().find(|item| item.name == name)
+    }
+
+    /// Works out how much of each raw resource the refinery needs to make the cost's materials.
+    /// Materials without a recipe are listed by their own name.
+    pub fn raw_resources(&self, cost: Cost) -> Vec<(String, u32)> {
+        let mut resources: Vec<(String, u32)> = Vec::new();
+        let mut add =
+            |name: &str, amount: u32| match resources.iter_mut().find(|(other, _)| other == name) {
+                Some((_, total)) => *total = total.saturating_add(amount),
+                None => resources.push((name.to_string(), amount)),
+            };
+        for (material, amount) in cost.iter() {
+            match self
+                .recipes
+                .iter()
+                .find(|recipe| recipe.material == material)
+            {
+                Some(recipe) => {
+                    for (resource, per_unit) in &recipe.inputs {
+                        add(resource, amount.saturating_mul(*per_unit));
+                    }
+                }
+                None => add(material.display_name(), amount),
+            }
+        }
+        resources
+    }
+
+    /// Finds the category which the item belongs to.
+    ///
+    /// This only works for items that come from this catalogue.
+    pub fn category_of(&self, item: &Item) -> Option<&Category> {
+        self.categories.get(item.category)
+    }
+
+    pub fn parse(source: &str, path: &str) -> Result<Self, CatalogueError> {
+        let error = |offset: usize, message: String| CatalogueError::Invalid {
+            path: path.to_string(),
+            line: line_of(source, offset),
+            message,
+        };
+
+        let raw: RawCatalogue = toml::from_str(source).map_err(|err| CatalogueError::Parse {
+            path: path.to_string(),
+            err,
+        })?;
+
+        if *raw.version.get_ref() != CATALOGUE_VERSION {
+            return Err(error(
+                raw.version.start(),
+                format!(
+                    "catalogue version {} is not supported, expected version {}",
+                    raw.version.get_ref(),
+                    CATALOGUE_VERSION
+                ),
+            ));
+        }
+        let queue_discounts = raw.queue_discounts.get_ref();
+        if queue_discounts.is_empty() {
+            return Err(error(
+                raw.queue_discounts.start(),
+                "`queue_discounts` needs at least one entry".to_string(),
+            ));
+        }
+        if let Some(discount) = queue_discounts.iter().find(|discount| **discount > 100) {
+            return Err(error(
+                raw.queue_discounts.start(),
+                format!("a discount of {discount}% is more than 100%"),
+            ));
+        }
+        let max_crates = queue_discounts.len() as u32;
+
+        let mut recipes = Vec::new();
+        if let Some(raw_recipes) = &raw.recipes {
+            for (key, inputs) in raw_recipes.get_ref() {
+                let material = Material::from_key(key).ok_or_else(|| {
+                    error(
+                        raw_recipes.start(),
+                        format!("there is a recipe for `{key}`, which is not a material"),
+                    )
+                })?;
+                if inputs.is_empty() {
+                    return Err(error(
+                        raw_recipes.start(),
+                        format!("the recipe for `{key}` doesn't take any resources"),
+                    ));
+                }
+                recipes.push(Recipe {
+                    material,
+                    inputs: inputs
+                        .iter()
+                        .map(|(resource, amount)| (resource.clone(), *amount))
+                        .collect(),
+                });
+            }
+        }
+        recipes.sort_by_key(|recipe| recipe.material.index());
+
+        let mut names: Vec<&Spanned<String>> = Vec::new();
+        let mut ids: Vec<(String, usize)> = Vec::new();
+        let mut categories = Vec::with_capacity(raw.category.len());
+        for (category_index, category) in raw.category.iter().enumerate() {
+            if category.name.get_ref().trim().is_empty() {
+                return Err(error(
+                    category.name.start(),
+                    "category name is empty".to_string(),
+                ));
+            }
+
+            let category_crates = category.crates_per_queue.unwrap_or(max_crates);
+            let category_units = category.units_per_crate.unwrap_or(1);
+            if !(1..=MAX_UNITS_PER_CRATE).contains(&category_units) {
+                return Err(error(
+                    category.name.start(),
+                    format!(
+                        "category `{}` has {category_units} units per crate, but a crate holds \
+                         between 1 and {MAX_UNITS_PER_CRATE}",
+                        category.name.get_ref()
+                    ),
+                ));
+            }
+            if !(1..=max_crates).contains(&category_crates) {
+                return Err(error(
+                    category.name.start(),
+                    format!(
+                        "category `{}` has {category_crates} crates per queue, but the MPF \
+                         takes between 1 and {max_crates}",
+                        category.name.get_ref()
+                    ),
+                ));
+            }
+
+            let mut items = Vec::with_capacity(category.items.len());
+            for (position, item) in category.items.iter().enumerate() {
+                let name = item.name.get_ref();
+                if name.trim().is_empty() {
+                    return Err(error(item.name.start(), "item name is empty".to_string()));
+                }
+                if let Some(previous) = names.iter().find(|other| other.get_ref() == name) {
+                    return Err(error(
+                        item.name.start(),
+                        format!(
+                            "item `{name}` is already defined at line {}",
+                            line_of(source, previous.start())
+                        ),
+                    ));
+                }
+                let mut crate_cost = Cost::default();
+                for (key, amount) in &item.materials {
+                    let material = Material::from_key(key).ok_or_else(|| {
+                        error(
+                            item.name.start(),
+                            format!("item `{name}` has an unknown field or material `{key}`"),
+                        )
+                    })?;
+                    if *amount > MAX_CRATE_COST {
+                        return Err(error(
+                            item.name.start(),
+                            format!(
+                                "item `{name}` costs {amount} {key} per crate, but the most a \
+                                 crate can cost is {MAX_CRATE_COST}"
+                            ),
+                        ));
+                    }
+                    crate_cost[material] = *amount;
+                }
+                if crate_cost.is_zero() {
+                    return Err(error(
+                        item.name.start(),
+                        format!("item `{name}` does not cost any materials"),
+                    ));
+                }
+                let crates_per_queue = item.crates_per_queue.unwrap_or(category_crates);
+                if !(1..=max_crates).contains(&crates_per_queue) {
+                    return Err(error(
+                        item.name.start(),
+                        format!(
+                            "item `{name}` has {crates_per_queue} crates per queue, but the MPF \
+                             takes between 1 and {max_crates}"
+                        ),
+                    ));
+                }
+                let units_per_crate = item.units_per_crate.unwrap_or(category_units);
+                if !(1..=MAX_UNITS_PER_CRATE).contains(&units_per_crate) {
+                    return Err(error(
+                        item.name.start(),
+                        format!(
+                            "item `{name}` has {units_per_crate} units per crate, but a crate \
+                             holds between 1 and {MAX_UNITS_PER_CRATE}"
+                        ),
+                    ));
+                }
+                let id = match &item.id {
+                    Some(id) => {
+                        if !is_valid_id(id) {
+                            return Err(error(
+                                item.name.start(),
+                                format!(
+                                    "item `{name}` has the ID `{id}`, but IDs can only contain \
+                                     lowercase letters, digits and underscores"
+                                ),
+                            ));
+                        }
+                        id.clone()
+                    }
+                    None => {
+                        let id = id_from_name(name);
+                        if id.is_empty() {
+                            return Err(error(
+                                item.name.start(),
+                                format!("item `{name}` needs an `id`, since its name has no letters or digits"),
+                            ));
+                        }
+                        id
+                    }
+                };
+                if let Some((_, previous)) = ids.iter().find(|(other, _)| *other == id) {
+                    return Err(error(
+                        item.name.start(),
+                        format!(
+                            "item `{name}` has the ID `{id}`, which the item at line {} \
+                             already has",
+                            line_of(source, *previous)
+                        ),
+                    ));
+                }
+                names.push(&item.name);
+                ids.push((id.clone(), item.name.start()));
+
+                let queue_cost = queue_discounts
+                    .iter()
+                    .take(crates_per_queue as usize)
+                    .fold(Cost::default(), |total, discount| {
+                        total + crate_cost.discounted(*discount)
+                    });
+
+                items.push(Item {
+                    id,
+                    name: name.clone(),
+                    short_name: item.short_name.clone(),
+                    crate_cost,
+                    crates_per_queue,
+                    units_per_crate,
+                    queue_cost,
+                    useless: item.useless,
+                    faction: item.faction,
+                    category: category_index,
+                    position,
+                });
+            }
+
+            categories.push(Category {
+                name: category.name.get_ref().clone(),
+                items,
+            });
+        }
+
+        Ok(Self {
+            categories,
+            recipes,
+        })
+    }
+}
+
+/// Makes an ID for an item which doesn't set one, like `no_2_loughcaster` for "No.2 Loughcaster".
+fn id_from_name(name: &str) -> String {
+    let mut id = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            id.push(c.to_ascii_lowercase());
+        } else if !id.is_empty() && !id.ends_with('_') {
+            id.push('_');
+        }
+    }
+    if id.ends_with('_') {
+        id.pop();
+    }
+    id
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Returns the 1-based line number of the given byte offset.
+pub fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawCatalogue {
+    version: Spanned<u32>,
+    queue_discounts: Spanned<Vec<u32>>,
+    recipes: Option<Spanned<BTreeMap<String, BTreeMap<String, u32>>>>,
+    #[serde(default)]
+    category: Vec<RawCategory>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawCategory {
+    name: Spanned<String>,
+    crates_per_queue: Option<u32>,
+    units_per_crate: Option<u32>,
+    #[serde(default)]
+    items: Vec<RawItem>,
+}
+
+/// Unknown fields aren't denied here, since any field besides the ones below is taken to be a
+/// material, which `Catalogue::parse` checks instead.
+#[derive(Deserialize)]
+struct RawItem {
+    id: Option<String>,
+    name: Spanned<String>,
+    short_name: Option<String>,
+    crates_per_queue: Option<u32>,
+    units_per_crate: Option<u32>,
+    #[serde(default)]
+    useless: bool,
+    faction: Option<Faction>,
+    #[serde(flatten)]
+    materials: BTreeMap<String, u32>,
+}
+
+#[derive(Debug)]
+pub enum CatalogueError {
+    Io {
+        path: String,
+        err: io::Error,
+    },
+    Parse {
+        path: String,
+        err: toml::de::Error,
+    },
+    Invalid {
+        path: String,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for CatalogueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, err } => write!(f, "could not read {path}: {err}"),
+            Self::Parse { path, err } => write!(f, "{path}: {err}"),
+            Self::Invalid {
+                path,
+                line,
+                message,
+            } => write!(f, "{path}:{line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for CatalogueError {}
@@ -0,0 +1,285 @@
+use std::fmt;
+
+use fstlg::{
+    catalogue::{
+        Catalogue,
+        Faction,
+        Item,
+    },
+    fuzzy::{
+        edit_distance,
+        fuzzy_score,
+    },
+    inventory::{
+        Inventory,
+        InventoryError,
+    },
+    output::{
+        format_raw_resources,
+        format_todolist,
+        OutputFormat,
+    },
+    profile::Profile,
+    todolist::Todolist,
+};
+
+use crate::config::Config;
+
+const USAGE: &str = "\
+Usage: fstlg [--faction warden|colonial] [--add ITEM[:COUNT]]... [--format FORMAT] [--resources]
+             [--target-levels] [--inventory FILE] [--profile NAME]
+
+Without any arguments, the interactive todo-list generator starts. With arguments, the todo-list
+is built from the `--add` items and printed instead.
+
+Options:
+  --faction FACTION   Which faction's items to use, `warden` (the default) or `colonial`
+  --add ITEM[:COUNT]  Adds an item by its name or short name, one queue unless a count is given
+  --format FORMAT     The name of a template, `discord` or `plain`
+  --resources         Prints the raw resources needed to refine the materials instead
+  --target-levels     Reads the `--add` counts as crates to keep in stock, and orders whatever
+                      is missing in whole queues
+  --inventory FILE    What the stockpile already holds, as a TSV or CSV export; this implies
+                      `--target-levels`
+  --profile NAME      Adds the target levels of a profile from `profiles.toml`; this implies
+                      `--target-levels`
+  --help              Prints this message
+";
+
+/// How many similarly named items are suggested when an item can't be found.
+const SUGGESTIONS: usize = 3;
+/// The largest count `--add` takes, in queues or target crates. Anything more is surely a typo.
+const MAX_COUNT: u32 = 10_000;
+
+/// Builds a todo-list from command-line arguments and returns it formatted, without starting the
+/// interactive UI.
+pub fn run(
+    args: &[String],
+    catalogue: &'static Catalogue,
+    config: &'static Config,
+    profiles: &[Profile],
+) -> Result<String, CliError> {
+    let mut faction = Faction::Warden;
+    let mut format = OutputFormat::Template(config.default_template());
+    let mut additions = Vec::new();
+    let mut todolist = Todolist::default();
+    let mut raw_resources = false;
+    let mut inventory = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| CliError::MissingValue(arg.clone()))
+        };
+        match arg.as_str() {
+            "--help" | "-h" => return Ok(USAGE.to_string()),
+            "--faction" => {
+                let value = value()?;
+                faction = match value.to_lowercase().as_str() {
+                    "warden" | "wardens" => Faction::Warden,
+                    "colonial" | "colonials" => Faction::Colonial,
+                    _ => return Err(CliError::UnknownFaction(value.clone())),
+                };
+            }
+            "--format" => format = parse_format(value()?, config)?,
+            "--add" => additions.push(parse_addition(value()?)?),
+            "--resources" => raw_resources = true,
+            "--target-levels" => todolist.target_levels = true,
+            "--inventory" => {
+                inventory =
+                    Some(Inventory::load(value()?, catalogue).map_err(CliError::Inventory)?);
+                todolist.target_levels = true;
+            }
+            "--profile" => {
+                let name = value()?;
+                let profile = profiles
+                    .iter()
+                    .find(|profile| profile.name.eq_ignore_ascii_case(name))
+                    .ok_or_else(|| CliError::UnknownProfile(name.clone()))?;
+                for entry in profile.entries() {
+                    todolist.add(entry.item, entry.count);
+                }
+                todolist.target_levels = true;
+            }
+            _ => return Err(CliError::UnknownArgument(arg.clone())),
+        }
+    }
+
+    for (name, count) in additions {
+        todolist.add(find_item(catalogue, faction, name)?, count);
+    }
+    let entries = todolist.order(inventory.as_ref());
+
+    if raw_resources {
+        Ok(format_raw_resources(&entries, catalogue))
+    } else {
+        Ok(format_todolist(&entries, catalogue, format))
+    }
+}
+
+fn parse_format(name: &str, config: &'static Config) -> Result<OutputFormat<'static>, CliError> {
+    if let Some(template) = config
+        .templates
+        .iter()
+        .find(|template| template.name.eq_ignore_ascii_case(name))
+    {
+        return Ok(OutputFormat::Template(template));
+    }
+    match name.to_lowercase().as_str() {
+        "discord" => Ok(OutputFormat::Discord),
+        "plain" => Ok(OutputFormat::Plain),
+        _ => Err(CliError::UnknownFormat(name.to_string())),
+    }
+}
+
+/// Splits `ITEM[:COUNT]` into the item's name and count. Names can contain colons
+/// themselves, so only a number after the last one is taken as the count.
+fn parse_addition(value: &str) -> Result<(&str, u32), CliError> {
+    match value.rsplit_once(':') {
+        Some((name, count)) if !count.is_empty() && count.bytes().all(|b| b.is_ascii_digit()) => {
+            match count.parse() {
+                Ok(count) if (1..=MAX_COUNT).contains(&count) => Ok((name.trim(), count)),
+                _ => Err(CliError::InvalidCount(value.to_string())),
+            }
+        }
+        _ => Ok((value.trim(), 1)),
+    }
+}
+
+/// Finds the item with the given ID, name or short name, ignoring case. Items of the other faction
+/// aren't considered, so shared short names like "Flamethrower" pick the faction's own item.
+fn find_item(
+    catalogue: &'static Catalogue,
+    faction: Faction,
+    name: &str,
+) -> Result<&'static Item, CliError> {
+    let items = || catalogue.items();
+    let matches = |item: &&Item| {
+        item.id.eq_ignore_ascii_case(name)
+            || item.name.eq_ignore_ascii_case(name)
+            || item
+                .short_name
+                .as_deref()
+                .is_some_and(|short_name| short_name.eq_ignore_ascii_case(name))
+    };
+
+    if let Some(item) = items()
+        .filter(|item| item.faction.is_none_or(|fac| fac == faction))
+        .find(matches)
+    {
+        return Ok(item);
+    }
+    if let Some(item) = items().find(matches) {
+        return Err(CliError::WrongFaction {
+            item: item.name.clone(),
+            faction: item.faction.unwrap_or(faction),
+        });
+    }
+
+    // Suggest items the name is an abbreviation of, and failing that, ones it's a typo of.
+    let candidates = || {
+        items()
+            .filter(|item| item.faction.is_none_or(|fac| fac == faction))
+            .flat_map(|item| {
+                std::iter::once(item.name.as_str())
+                    .chain(item.short_name.as_deref())
+                    .map(move |candidate| (item, candidate))
+            })
+    };
+    let mut suggestions: Vec<(usize, &Item)> = candidates()
+        .filter_map(|(item, candidate)| {
+            fuzzy_score(name, candidate).map(|score| (usize::MAX - score as usize, item))
+        })
+        .collect();
+    if suggestions.is_empty() {
+        suggestions = candidates()
+            .map(|(item, candidate)| (edit_distance(name, candidate), item))
+            .filter(|(distance, _)| *distance <= name.chars().count() / 2)
+            .collect();
+    }
+    suggestions.sort_by_key(|(rank, _)| *rank);
+    let mut seen = Vec::new();
+    suggestions.retain(|(_, item)| {
+        let new = !seen.iter().any(|other| std::ptr::eq(*other, *item));
+        seen.push(*item);
+        new
+    });
+    Err(CliError::UnknownItem {
+        name: name.to_string(),
+        suggestions: suggestions
+            .into_iter()
+            .take(SUGGESTIONS)
+            .map(|(_, item)| item.display_name().to_string())
+            .collect(),
+    })
+}
+
+#[derive(Debug)]
+pub enum CliError {
+    UnknownArgument(String),
+    MissingValue(String),
+    UnknownFaction(String),
+    UnknownFormat(String),
+    InvalidCount(String),
+    Inventory(InventoryError),
+    UnknownProfile(String),
+    UnknownItem {
+        name: String,
+        suggestions: Vec<String>,
+    },
+    WrongFaction {
+        item: String,
+        faction: Faction,
+    },
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownArgument(arg) => {
+                write!(f, "unknown argument `{arg}`, see `fstlg --help`")
+            }
+            Self::MissingValue(arg) => write!(f, "`{arg}` needs a value"),
+            Self::UnknownFaction(faction) => {
+                write!(
+                    f,
+                    "unknown faction `{faction}`, expected `warden` or `colonial`"
+                )
+            }
+            Self::UnknownFormat(format) => write!(
+                f,
+                "unknown format `{format}`, expected the name of a template, `discord` or `plain`"
+            ),
+            Self::InvalidCount(value) => {
+                write!(f, "`{value}` needs a count from 1 to {MAX_COUNT}")
+            }
+            Self::Inventory(err) => write!(f, "{err}"),
+            Self::UnknownProfile(name) => write!(f, "there is no profile named `{name}`"),
+            Self::UnknownItem { name, suggestions } => {
+                write!(f, "no item is called `{name}`")?;
+                if !suggestions.is_empty() {
+                    write!(f, ", did you mean ")?;
+                    for (n, suggestion) in suggestions.iter().enumerate() {
+                        let separator = match n {
+                            0 => "",
+                            n if n + 1 == suggestions.len() => " or ",
+                            _ => ", ",
+                        };
+                        write!(f, "{separator}`{suggestion}`")?;
+                    }
+                    write!(f, "?")?;
+                }
+                Ok(())
+            }
+            Self::WrongFaction { item, faction } => {
+                write!(
+                    f,
+                    "`{item}` is a {faction:?} item, use `--faction` to switch"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
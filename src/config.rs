@@ -0,0 +1,184 @@
+use std::{
+    fmt,
+    fs,
+    io,
+    path::PathBuf,
+};
+
+use fstlg::{
+    catalogue::line_of,
+    template::Template,
+};
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::{
+    keymap::{
+        Keymap,
+        RawKeymap,
+    },
+    output_file::OutputFile,
+};
+
+/// Where the user's settings are looked for, relative to the working directory.
+const CONFIG_PATH: &str = "config.toml";
+
+pub struct Config {
+    /// The output templates, starting with the default one.
+    pub templates: Vec<Template>,
+    pub output_file: OutputFile,
+    pub keymap: Keymap,
+}
+
+impl Config {
+    /// Loads `config.toml` from the working directory if there is one, and uses the defaults
+    /// otherwise.
+    pub fn load() -> Result<Self, ConfigError> {
+        match fs::read_to_string(CONFIG_PATH) {
+            Ok(source) => Self::parse(&source, CONFIG_PATH),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(ConfigError::Io {
+                path: CONFIG_PATH.to_string(),
+                err,
+            }),
+        }
+    }
+
+    pub fn parse(source: &str, path: &str) -> Result<Self, ConfigError> {
+        let error = |offset: usize, message: String| ConfigError::Invalid {
+            path: path.to_string(),
+            line: line_of(source, offset),
+            message,
+        };
+
+        let raw: RawConfig = toml::from_str(source).map_err(|err| ConfigError::Parse {
+            path: path.to_string(),
+            err,
+        })?;
+
+        let mut config = Self::default();
+        for (n, template) in raw.template.iter().enumerate() {
+            let name = template.name.get_ref();
+            if raw.template[..n]
+                .iter()
+                .any(|other| other.name.get_ref() == name)
+            {
+                return Err(error(
+                    template.name.start(),
+                    format!("there is already a template named `{name}`"),
+                ));
+            }
+
+            let parsed = Template::parse(
+                name,
+                template.entry.get_ref(),
+                template
+                    .total
+                    .as_ref()
+                    .map(|total| total.get_ref().as_str()),
+            )
+            .map_err(|err| error(template.name.start(), format!("template `{name}`: {err}")))?;
+
+            // A template with the same name as the built-in one replaces it.
+            match config
+                .templates
+                .iter_mut()
+                .find(|other| other.name == *name)
+            {
+                Some(builtin) => *builtin = parsed,
+                None => config.templates.push(parsed),
+            }
+        }
+
+        if let Some(output) = &raw.output {
+            if let Some(file_name) = &output.file_name {
+                config.output_file = OutputFile::new(file_name.get_ref())
+                    .map_err(|err| error(file_name.start(), err.to_string()))?;
+            }
+            if let Some(directory) = &output.directory {
+                config.output_file.directory = PathBuf::from(directory);
+            }
+        }
+
+        if let Some(keys) = &raw.keys {
+            config.keymap = Keymap::new(
+                keys.get_ref().preset.as_deref().unwrap_or("default"),
+                &keys.get_ref().bindings,
+            )
+            .map_err(|err| error(keys.start(), err.to_string()))?;
+        }
+
+        Ok(config)
+    }
+
+    pub fn default_template(&self) -> &Template {
+        &self.templates[0]
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            templates: vec![Template::builtin()],
+            output_file: OutputFile::default(),
+            keymap: Keymap::default(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+    #[serde(default)]
+    template: Vec<RawTemplate>,
+    output: Option<RawOutput>,
+    keys: Option<Spanned<RawKeymap>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTemplate {
+    name: Spanned<String>,
+    entry: Spanned<String>,
+    total: Option<Spanned<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawOutput {
+    directory: Option<String>,
+    file_name: Option<Spanned<String>>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io {
+        path: String,
+        err: io::Error,
+    },
+    Parse {
+        path: String,
+        err: toml::de::Error,
+    },
+    Invalid {
+        path: String,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, err } => write!(f, "could not read {path}: {err}"),
+            Self::Parse { path, err } => write!(f, "{path}: {err}"),
+            Self::Invalid {
+                path,
+                line,
+                message,
+            } => write!(f, "{path}:{line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
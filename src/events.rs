@@ -0,0 +1,27 @@
+use std::time::Duration;
+
+use crossterm::event::Event;
+
+/// Where the app gets its key presses and mouse events from. This is the terminal, except in
+/// tests, which drive the app with a script of events instead.
+pub trait EventSource {
+    /// Waits up to `timeout` for the next event, and returns `None` if there wasn't one.
+    fn next_event(&mut self, timeout: Duration) -> Option<Event>;
+
+    /// Whether there won't be any more events, like when a script has run out. The app stops
+    /// once it's drawn what the last event did.
+    fn finished(&self) -> bool {
+        false
+    }
+}
+
+/// The events of the terminal the app is running in.
+pub struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+    fn next_event(&mut self, timeout: Duration) -> Option<Event> {
+        crossterm::event::poll(timeout)
+            .unwrap()
+            .then(|| crossterm::event::read().unwrap())
+    }
+}
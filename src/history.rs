@@ -0,0 +1,44 @@
+/// How many changes are remembered before the oldest ones are forgotten.
+const MAX_LENGTH: usize = 100;
+
+/// Earlier states of something that can be undone and redone, each with a description of the
+/// change that was made to it.
+pub struct History<T> {
+    undo: Vec<(String, T)>,
+    redo: Vec<(String, T)>,
+}
+
+impl<T> History<T> {
+    pub fn new() -> Self {
+        Self {
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    /// Remembers the state from before a change. Anything that was undone can't be redone after
+    /// this anymore.
+    pub fn record(&mut self, description: String, before: T) {
+        if self.undo.len() == MAX_LENGTH {
+            self.undo.remove(0);
+        }
+        self.undo.push((description, before));
+        self.redo.clear();
+    }
+
+    /// Steps back to the state before the last change, returning it with the change's
+    /// description.
+    pub fn undo(&mut self, current: T) -> Option<(String, T)> {
+        let (description, before) = self.undo.pop()?;
+        self.redo.push((description.clone(), current));
+        Some((description, before))
+    }
+
+    /// Steps forward to the state after the last undone change, returning it with the change's
+    /// description.
+    pub fn redo(&mut self, current: T) -> Option<(String, T)> {
+        let (description, after) = self.redo.pop()?;
+        self.undo.push((description.clone(), current));
+        Some((description, after))
+    }
+}
@@ -0,0 +1,223 @@
+use std::{
+    fmt,
+    fs,
+    io,
+};
+
+use crate::{
+    catalogue::{
+        Catalogue,
+        Item,
+    },
+    todolist::TodolistEntry,
+};
+
+/// What a stockpile already holds, in crates.
+pub struct Inventory<'a> {
+    /// The file the inventory was read from.
+    pub path: String,
+    stock: Vec<(&'a Item, u32)>,
+    /// Names from the file which aren't in the catalogue, like raw resources.
+    pub unmatched: Vec<String>,
+}
+
+impl<'a> Inventory<'a> {
+    pub fn load(path: &str, catalogue: &'a Catalogue) -> Result<Self, InventoryError> {
+        let source = fs::read_to_string(path).map_err(|err| InventoryError::Io {
+            path: path.to_string(),
+            err,
+        })?;
+        Self::parse(&source, path, catalogue)
+    }
+
+    /// Reads a stockpile export in TSV or CSV form, like the ones stockpile-reading tools such
+    /// as FIR produce.
+    ///
+    /// The first line has to name the columns. `Name` and `Quantity` are needed, and if there's
+    /// a `Crated?` column, loose items are counted as whole crates using the `Per Crate` column.
+    pub fn parse(
+        source: &str,
+        path: &str,
+        catalogue: &'a Catalogue,
+    ) -> Result<Self, InventoryError> {
+        let error = |line: usize, message: String| InventoryError::Invalid {
+            path: path.to_string(),
+            line,
+            message,
+        };
+
+        let mut lines = source
+            .lines()
+            .enumerate()
+            .map(|(n, line)| (n + 1, line))
+            .filter(|(_, line)| !line.trim().is_empty());
+        let (header_line, header) = lines
+            .next()
+            .ok_or_else(|| error(1, "the file is empty".to_string()))?;
+        let delimiter = if header.contains('\t') { '\t' } else { ',' };
+        let header = split_row(header, delimiter);
+        let column = |name: &str| {
+            header
+                .iter()
+                .position(|column| column.trim().eq_ignore_ascii_case(name))
+        };
+        let name_column = column("Name")
+            .ok_or_else(|| error(header_line, "there is no `Name` column".to_string()))?;
+        let quantity_column = column("Quantity")
+            .ok_or_else(|| error(header_line, "there is no `Quantity` column".to_string()))?;
+        let crated_column = column("Crated?");
+        let per_crate_column = column("Per Crate");
+
+        let mut inventory = Self {
+            path: path.to_string(),
+            stock: Vec::new(),
+            unmatched: Vec::new(),
+        };
+        for (line, row) in lines {
+            let row = split_row(row, delimiter);
+            let field = |column: usize| row.get(column).map_or("", |field| field.trim());
+
+            let name = field(name_column);
+            let quantity: u32 = field(quantity_column).parse().map_err(|_| {
+                error(
+                    line,
+                    format!("`{}` is not a quantity", field(quantity_column)),
+                )
+            })?;
+            let crated =
+                crated_column.is_none_or(|column| !field(column).eq_ignore_ascii_case("false"));
+            let crates = if crated {
+                quantity
+            } else {
+                match per_crate_column.and_then(|column| field(column).parse::<u32>().ok()) {
+                    Some(per_crate) if per_crate > 0 => quantity / per_crate,
+                    _ => continue,
+                }
+            };
+
+            match find_item(catalogue, name) {
+                Some(item) => inventory.add(item, crates),
+                None => {
+                    if !inventory.unmatched.iter().any(|other| other == name) {
+                        inventory.unmatched.push(name.to_string());
+                    }
+                }
+            }
+        }
+        Ok(inventory)
+    }
+
+    fn add(&mut self, item: &'a Item, crates: u32) {
+        match self.stock.iter_mut().find(|(other, _)| other.id == item.id) {
+            Some((_, total)) => *total = total.saturating_add(crates),
+            None => self.stock.push((item, crates)),
+        }
+    }
+
+    /// How many crates of the item the stockpile holds.
+    pub fn crates_of(&self, item: &Item) -> u32 {
+        self.stock
+            .iter()
+            .find(|(other, _)| other.id == item.id)
+            .map_or(0, |(_, crates)| *crates)
+    }
+
+    /// How many different catalogue items the stockpile holds.
+    pub fn item_count(&self) -> usize {
+        self.stock.len()
+    }
+}
+
+/// Works out what to order when the entries hold how many crates of each item to keep in stock,
+/// rather than how many queues to order. Whatever the inventory is short of is rounded up to
+/// whole MPF queues, and items which are stocked well enough are left out.
+pub fn shortfall<'a>(
+    entries: &[TodolistEntry<'a>],
+    inventory: Option<&Inventory>,
+) -> Vec<TodolistEntry<'a>> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let held = inventory.map_or(0, |inventory| inventory.crates_of(entry.item));
+            let missing = entry.count.saturating_sub(held);
+            let crates_per_queue = entry.item.crates_per_queue;
+            let queues = missing.div_ceil(crates_per_queue);
+            (queues > 0).then_some(TodolistEntry {
+                item: entry.item,
+                count: queues,
+                priority: entry.priority,
+            })
+        })
+        .collect()
+}
+
+/// Finds an item by its name, ignoring case, or else by its short name. Some short names, like
+/// "Truck", are shared by both factions' versions of an item, and those are only matched by their
+/// full name.
+fn find_item<'a>(catalogue: &'a Catalogue, name: &str) -> Option<&'a Item> {
+    if let Some(item) = catalogue
+        .items()
+        .find(|item| item.name.eq_ignore_ascii_case(name))
+    {
+        return Some(item);
+    }
+    let mut short_name_matches = catalogue.items().filter(|item| {
+        item.short_name
+            .as_deref()
+            .is_some_and(|short_name| short_name.eq_ignore_ascii_case(name))
+    });
+    match (short_name_matches.next(), short_name_matches.next()) {
+        (Some(item), None) => Some(item),
+        _ => None,
+    }
+}
+
+/// Splits a row into its fields, taking quotes into account so that fields can contain the
+/// delimiter. Two quotes in a row inside a quoted field stand for a literal quote.
+fn split_row(row: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = row.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+#[derive(Debug)]
+pub enum InventoryError {
+    Io {
+        path: String,
+        err: io::Error,
+    },
+    Invalid {
+        path: String,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for InventoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, err } => write!(f, "could not read {path}: {err}"),
+            Self::Invalid {
+                path,
+                line,
+                message,
+            } => write!(f, "{path}:{line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for InventoryError {}
@@ -0,0 +1,524 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+};
+
+use crossterm::event::{
+    KeyCode,
+    KeyEvent,
+    KeyModifiers,
+};
+use serde::Deserialize;
+
+/// Everything that can be bound to a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    FocusAddList,
+    FocusTodolist,
+    Up,
+    Down,
+    First,
+    Last,
+    /// Adds the selected item in the "Add" list, or removes the selected todo-list entry.
+    Select,
+    Remove,
+    More,
+    Fewer,
+    MoveUp,
+    MoveDown,
+    Priority,
+    Undo,
+    Redo,
+    Write,
+    Copy,
+    Faction,
+    Format,
+    Save,
+    Notes,
+    Open,
+    Search,
+    RawResources,
+    Inventory,
+    TargetLevels,
+    NextProfile,
+    ApplyProfile,
+    SaveProfile,
+    Clear,
+    SortMode,
+    Help,
+    Quit,
+}
+
+impl Action {
+    /// Every action.
+    pub const ALL: [Self; 33] = [
+        Self::FocusAddList,
+        Self::FocusTodolist,
+        Self::Up,
+        Self::Down,
+        Self::First,
+        Self::Last,
+        Self::Select,
+        Self::Remove,
+        Self::More,
+        Self::Fewer,
+        Self::MoveUp,
+        Self::MoveDown,
+        Self::Priority,
+        Self::Undo,
+        Self::Redo,
+        Self::Write,
+        Self::Copy,
+        Self::Faction,
+        Self::Format,
+        Self::Save,
+        Self::Notes,
+        Self::Open,
+        Self::Search,
+        Self::RawResources,
+        Self::Inventory,
+        Self::TargetLevels,
+        Self::NextProfile,
+        Self::ApplyProfile,
+        Self::SaveProfile,
+        Self::Clear,
+        Self::SortMode,
+        Self::Help,
+        Self::Quit,
+    ];
+
+    /// The name the action goes by in `config.toml`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::FocusAddList => "focus_add_list",
+            Self::FocusTodolist => "focus_todolist",
+            Self::Up => "up",
+            Self::Down => "down",
+            Self::First => "first",
+            Self::Last => "last",
+            Self::Select => "select",
+            Self::Remove => "remove",
+            Self::More => "more",
+            Self::Fewer => "fewer",
+            Self::MoveUp => "move_up",
+            Self::MoveDown => "move_down",
+            Self::Priority => "priority",
+            Self::Undo => "undo",
+            Self::Redo => "redo",
+            Self::Write => "write",
+            Self::Copy => "copy",
+            Self::Faction => "faction",
+            Self::Format => "format",
+            Self::Save => "save",
+            Self::Notes => "notes",
+            Self::Open => "open",
+            Self::Search => "search",
+            Self::RawResources => "raw_resources",
+            Self::Inventory => "inventory",
+            Self::TargetLevels => "target_levels",
+            Self::NextProfile => "next_profile",
+            Self::ApplyProfile => "apply_profile",
+            Self::SaveProfile => "save_profile",
+            Self::Clear => "clear",
+            Self::SortMode => "sort_mode",
+            Self::Help => "help",
+            Self::Quit => "quit",
+        }
+    }
+
+    /// Whether the action works on the selected todo-list entry.
+    pub fn is_entry_action(self) -> bool {
+        matches!(
+            self,
+            Self::Remove
+                | Self::More
+                | Self::Fewer
+                | Self::MoveUp
+                | Self::MoveDown
+                | Self::Priority
+        )
+    }
+
+    /// Whether the action does anything while the "Add" list has focus, or while the todo-list
+    /// has.
+    pub fn applies_to(self, add_list: bool) -> bool {
+        match self {
+            Self::FocusAddList => !add_list,
+            Self::FocusTodolist => add_list,
+            action => !(add_list && action.is_entry_action()),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    pub fn description(self, add_list: bool) -> &'static str {
+        match self {
+            Self::FocusAddList => "Go to the \"Add\" list",
+            Self::FocusTodolist => "Go to the todo-list",
+            Self::Up => "Select the previous item",
+            Self::Down => "Select the next item",
+            Self::First => "Select the first item",
+            Self::Last => "Select the last item",
+            Self::Select if add_list => "Add the selected item to the todo-list",
+            Self::Select | Self::Remove => "Remove the selected entry",
+            Self::More => "Order one more queue of the selected entry",
+            Self::Fewer => "Order one queue less of the selected entry",
+            Self::MoveUp => "Move the selected entry up",
+            Self::MoveDown => "Move the selected entry down",
+            Self::Priority => "Switch the selected entry's priority",
+            Self::Undo => "Undo the last change to the todo-list",
+            Self::Redo => "Redo the last undone change",
+            Self::Write => "Write the todo-list to a file",
+            Self::Copy => "Copy the todo-list to the clipboard",
+            Self::Faction => "Switch factions",
+            Self::Format => "Switch output formats",
+            Self::Save => "Save the todo-list",
+            Self::Notes => "Edit the notes",
+            Self::Open => "Open a saved todo-list",
+            Self::Search => "Search the \"Add\" list",
+            Self::RawResources => "Show the totals as materials or raw resources",
+            Self::Inventory => "Import a stockpile inventory",
+            Self::TargetLevels => "Switch between queues to order and target levels",
+            Self::NextProfile => "Pick the next target stock profile",
+            Self::ApplyProfile => "Fill the todo-list from the picked profile",
+            Self::SaveProfile => "Save the target levels as a profile",
+            Self::Clear => "Clear the todo-list",
+            Self::SortMode => "Switch between sorting by category and by hand",
+            Self::Help => "Show this help",
+            Self::Quit => "Quit",
+        }
+    }
+}
+
+/// A key with the modifiers held down along with it, written like `q`, `Enter` or `Ctrl+r`.
+///
+/// Shift isn't kept for characters, since it's already part of the character: `G` is shifted
+/// `g`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self { code, modifiers }
+    }
+
+    fn parse(source: &str) -> Option<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = source;
+        // `+` on its own, or after a modifier like in `Ctrl++`, is the plus key.
+        while let Some((modifier, key)) = rest.split_once('+').filter(|(_, key)| !key.is_empty()) {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return None,
+            };
+            rest = key;
+        }
+        let code = match rest.to_ascii_lowercase().as_str() {
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "space" => KeyCode::Char(' '),
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => return None,
+                }
+            }
+        };
+        Some(Self::new(code, modifiers))
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "Ctrl"),
+            (KeyModifiers::ALT, "Alt"),
+            (KeyModifiers::SHIFT, "Shift"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{name}+")?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::PageUp => write!(f, "PageUp"),
+            KeyCode::PageDown => write!(f, "PageDown"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+/// Which keys do what. Bindings can be sequences of keys, like `g g` in the vim preset.
+pub struct Keymap {
+    bindings: Vec<(Vec<Key>, Action)>,
+}
+
+/// What the keys pressed so far amount to.
+pub enum Lookup {
+    Action(Action),
+    /// The keys are the start of a longer binding.
+    Pending,
+    None,
+}
+
+impl Keymap {
+    /// Starts from the named preset, and replaces the keys of any action in `overrides`.
+    pub fn new(
+        preset: &str,
+        overrides: &BTreeMap<String, Vec<String>>,
+    ) -> Result<Self, KeymapError> {
+        let preset = match preset {
+            "default" => DEFAULT_PRESET,
+            "vim" => VIM_PRESET,
+            _ => return Err(KeymapError::UnknownPreset(preset.to_string())),
+        };
+        let mut bindings: Vec<(Action, Vec<String>)> = preset
+            .iter()
+            .map(|(action, keys)| (*action, keys.iter().map(|key| key.to_string()).collect()))
+            .collect();
+        for (name, keys) in overrides {
+            let action =
+                Action::from_name(name).ok_or_else(|| KeymapError::UnknownAction(name.clone()))?;
+            match bindings.iter_mut().find(|(other, _)| *other == action) {
+                Some((_, old_keys)) => *old_keys = keys.clone(),
+                None => bindings.push((action, keys.clone())),
+            }
+        }
+
+        let mut keymap = Self {
+            bindings: Vec::new(),
+        };
+        for (action, sequences) in bindings {
+            for source in sequences {
+                let sequence = source
+                    .split_whitespace()
+                    .map(|key| {
+                        Key::parse(key).ok_or_else(|| KeymapError::InvalidKey(source.clone()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if sequence.is_empty() {
+                    return Err(KeymapError::InvalidKey(source));
+                }
+                // A binding which another one starts with would always fire first, so the longer
+                // one could never be used.
+                if let Some((other, other_action)) = keymap
+                    .bindings
+                    .iter()
+                    .find(|(other, _)| other.starts_with(&sequence) || sequence.starts_with(other))
+                {
+                    return Err(KeymapError::Conflict {
+                        keys: format_sequence(&sequence),
+                        action,
+                        other_keys: format_sequence(other),
+                        other_action: *other_action,
+                    });
+                }
+                keymap.bindings.push((sequence, action));
+            }
+        }
+        Ok(keymap)
+    }
+
+    pub fn lookup(&self, keys: &[Key]) -> Lookup {
+        let mut lookup = Lookup::None;
+        for (sequence, action) in &self.bindings {
+            if sequence == keys {
+                return Lookup::Action(*action);
+            }
+            if sequence.starts_with(keys) {
+                lookup = Lookup::Pending;
+            }
+        }
+        lookup
+    }
+
+    /// The keys bound to the action, like `Enter` or `g g, Home`.
+    pub fn keys(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .filter(|(_, other)| *other == action)
+            .map(|(sequence, _)| format_sequence(sequence))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Lists the actions that apply to the focused list and have keys bound to them, along with
+    /// the keys.
+    pub fn help(&self, add_list: bool) -> Vec<(String, &'static str)> {
+        Action::ALL
+            .into_iter()
+            .filter(|action| action.applies_to(add_list))
+            .map(|action| (self.keys(action), action.description(add_list)))
+            .filter(|(keys, _)| !keys.is_empty())
+            .collect()
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new("default", &BTreeMap::new()).expect("the default keymap should always be valid")
+    }
+}
+
+fn format_sequence(sequence: &[Key]) -> String {
+    sequence
+        .iter()
+        .map(Key::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The `[keys]` table of `config.toml`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RawKeymap {
+    pub preset: Option<String>,
+    /// Keys by action name, which replace the preset's keys for that action.
+    #[serde(default)]
+    pub bindings: BTreeMap<String, Vec<String>>,
+}
+
+const DEFAULT_PRESET: &[(Action, &[&str])] = &[
+    (Action::FocusAddList, &["Left"]),
+    (Action::FocusTodolist, &["Right"]),
+    (Action::Up, &["Up"]),
+    (Action::Down, &["Down"]),
+    (Action::First, &["Home"]),
+    (Action::Last, &["End"]),
+    (Action::Select, &["Enter"]),
+    (Action::Remove, &["Delete"]),
+    (Action::More, &["+", "="]),
+    (Action::Fewer, &["-"]),
+    (Action::MoveUp, &["Shift+Up"]),
+    (Action::MoveDown, &["Shift+Down"]),
+    (Action::Priority, &["!"]),
+    (Action::Undo, &["u"]),
+    (Action::Redo, &["Ctrl+r"]),
+    (Action::Write, &["w"]),
+    (Action::Copy, &["c"]),
+    (Action::Faction, &["f"]),
+    (Action::Format, &["t"]),
+    (Action::Save, &["s"]),
+    (Action::Notes, &["n"]),
+    (Action::Open, &["o"]),
+    (Action::Search, &["/"]),
+    (Action::RawResources, &["r"]),
+    (Action::Inventory, &["i"]),
+    (Action::TargetLevels, &["l"]),
+    (Action::NextProfile, &["p"]),
+    (Action::ApplyProfile, &["g"]),
+    (Action::SaveProfile, &["P"]),
+    (Action::Clear, &["x"]),
+    (Action::SortMode, &["m"]),
+    (Action::Help, &["?"]),
+    (Action::Quit, &["q"]),
+];
+
+/// Moves with `hjkl`, jumps with `g g` and `G`, and removes entries with `d d`. The keys these
+/// take over from the default preset move elsewhere: target levels to `T`, applying a profile to
+/// `a`, copying to `y` and clearing the todo-list to `D`.
+const VIM_PRESET: &[(Action, &[&str])] = &[
+    (Action::FocusAddList, &["h", "Left"]),
+    (Action::FocusTodolist, &["l", "Right"]),
+    (Action::Up, &["k", "Up"]),
+    (Action::Down, &["j", "Down"]),
+    (Action::First, &["g g", "Home"]),
+    (Action::Last, &["G", "End"]),
+    (Action::Select, &["Enter"]),
+    (Action::Remove, &["d d", "Delete"]),
+    (Action::More, &["+", "="]),
+    (Action::Fewer, &["-"]),
+    (Action::MoveUp, &["K", "Shift+Up"]),
+    (Action::MoveDown, &["J", "Shift+Down"]),
+    (Action::Priority, &["!"]),
+    (Action::Undo, &["u"]),
+    (Action::Redo, &["Ctrl+r"]),
+    (Action::Write, &["w"]),
+    (Action::Copy, &["y"]),
+    (Action::Faction, &["f"]),
+    (Action::Format, &["t"]),
+    (Action::Save, &["s"]),
+    (Action::Notes, &["n"]),
+    (Action::Open, &["o"]),
+    (Action::Search, &["/"]),
+    (Action::RawResources, &["r"]),
+    (Action::Inventory, &["i"]),
+    (Action::TargetLevels, &["T"]),
+    (Action::NextProfile, &["p"]),
+    (Action::ApplyProfile, &["a"]),
+    (Action::SaveProfile, &["P"]),
+    (Action::Clear, &["D"]),
+    (Action::SortMode, &["m"]),
+    (Action::Help, &["?"]),
+    (Action::Quit, &["q"]),
+];
+
+#[derive(Debug)]
+pub enum KeymapError {
+    UnknownPreset(String),
+    UnknownAction(String),
+    InvalidKey(String),
+    Conflict {
+        keys: String,
+        action: Action,
+        other_keys: String,
+        other_action: Action,
+    },
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownPreset(name) => write!(
+                f,
+                "unknown key preset `{name}`, expected `default` or `vim`"
+            ),
+            Self::UnknownAction(name) => write!(f, "there is no action named `{name}`"),
+            Self::InvalidKey(key) => write!(f, "`{key}` is not a key"),
+            Self::Conflict {
+                keys,
+                action,
+                other_keys,
+                other_action,
+            } => write!(
+                f,
+                "`{keys}` for `{}` clashes with `{other_keys}` for `{}`",
+                action.name(),
+                other_action.name()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for KeymapError {}
@@ -0,0 +1,12 @@
+//! The item catalogue, todo-lists and their output formats, without any of the UI. The `fstlg`
+//! binary is a terminal UI and command line over this.
+
+pub mod catalogue;
+pub mod fuzzy;
+pub mod inventory;
+pub mod material;
+pub mod output;
+pub mod profile;
+pub mod save;
+pub mod template;
+pub mod todolist;
@@ -1,10 +1,18 @@
-#![feature(strict_provenance)]
+mod cli;
+mod clipboard;
+mod config;
+mod events;
+mod history;
+mod keymap;
+mod output_file;
 
 use std::{
-    fmt::Write,
+    cmp::Reverse,
+    fmt,
+    fs,
     io,
-    iter,
     panic,
+    path::PathBuf,
     sync::{
         Arc,
         Mutex,
@@ -12,6 +20,7 @@ use std::{
     time::{
         Duration,
         Instant,
+        SystemTime,
     },
 };
 
@@ -21,12 +30,51 @@ use crossterm::{
         EnableMouseCapture,
         Event,
         KeyCode,
+        KeyEvent,
+        MouseButton,
+        MouseEvent,
+        MouseEventKind,
     },
     terminal::{
         EnterAlternateScreen,
         LeaveAlternateScreen,
     },
 };
+use fstlg::{
+    catalogue::{
+        Catalogue,
+        Cost,
+        Faction,
+        Item,
+    },
+    fuzzy::fuzzy_score,
+    inventory::{
+        shortfall,
+        Inventory,
+    },
+    output::{
+        format_queue_count,
+        format_todolist,
+        OutputFormat,
+    },
+    profile::{
+        Profile,
+        ProfileError,
+    },
+    save::{
+        self,
+        SaveError,
+        SavedEntry,
+        SavedList,
+        LISTS_DIR,
+    },
+    todolist::{
+        Priority,
+        SortMode,
+        Todolist,
+        TodolistEntry,
+    },
+};
 use tui::{
     backend::{
         Backend,
@@ -36,15 +84,21 @@ use tui::{
         Constraint,
         Direction,
         Layout,
+        Rect,
     },
     style::{
         Color,
         Modifier,
         Style,
     },
+    text::{
+        Span,
+        Spans,
+    },
     widgets::{
         Block,
         Borders,
+        Clear,
         List,
         ListItem,
         ListState,
@@ -54,116 +108,64 @@ use tui::{
     Terminal,
 };
 
-#[rustfmt::skip]
-const MPF_SMALL_ARMS: &[Item] = &[
-    Item::new("Booker Storm Rifle Model 838",       None,                       904,    0,      0,      0,  W),
-    Item::new("Aalto Storm Rifle 24",               None,                       904,    0,      0,      0,  W),
-    Item::new(r#""Dusk" ce.III"#,                    None,                       904,    0,      0,      0,  C),
-    Item::new("7.92mm",                             None,                       660,    0,      0,      0,  None),
-    Item::new("Malone MK.2",                        None,                       0,      0,      134,    0,  W),
-    Item::new("A3 Harpa Fragmentation Grenade",     Some("Harpa"),              550,    110,    0,      0,  W),
-    Item::new_useless("Cascadier 837",              None,                       330,    0,      0,      0,  W),
-    Item::new("Catara mo.II",                       Some("Catara"),             904,    0,      0,      0,  C),
-    Item::new("KRN886-127 Gast Machine Gun",        Some("Gast"),               0,      0,      134,    0,  C),
-    Item::new("Bombastone Grenade",                 Some("Bombastone"),         550,    110,    0,      0,  C),
-    Item::new_useless("8mm",                        None,                       220,    0,      0,      0,  None),
-    Item::new("Cometa T2-9",                        Some("Revolver"),           330,    0,      0,      0,  None),
-    Item::new("The Hangman 757",                    Some("Hangman"),            684,    0,      0,      0,  W),
-    Item::new("0.44",                               None,                       220,    0,      0,      0,  None),
-    Item::new("Sampo Auto-Rifle 77",                Some("Sampo"),              684,    0,      0,      0,  W),
-    Item::new("Blakerow 871",                       Some("Blakerow"),           770,    0,      0,      0,  W),
-    Item::new("Clancy Cinder M3",                   Some("Clancy Cinder"),      715,    0,      0,      0,  W),
-    Item::new("No.2 Loughcaster",                   Some("Loughcaster"),        550,    0,      0,      0,  W),
-    Item::new("Clancy-Raca M4",                     Some("Clancy-Raca"),        1100,   0,      79,     0,  W),
-    Item::new("Argenti r.II Rifle",                 Some("Argenti"),            550,    0,      0,      0,  C),
-    Item::new("Volta r.I Repeater",                 Some("Volta"),              550,    0,      0,      0,  C),
-    Item::new("Fuscina pi.I",                       Some("Fuscina"),            770,    0,      0,      0,  C),
-    Item::new("KRR2-790 Omen",                      Some("Omen"),               849,    0,      0,      0,  C),
-    Item::new("KRR3-792 Auger",                     Some("Auger"),              1100,   134,    0,      0,  C),
-    Item::new("7.62",                               None,                       440,    0,      0,      0,  None),
-    Item::new("Brasa Shotgun",                      Some("Shotgun"),            440,    0,      0,      0,  None),
-    Item::new("Buckshot",                           None,                       440,    0,      0,      0,  None),
-    Item::new(r#"No.1 "The Liar" Submachinegun"#,   Some("The Liar"),           660,    0,      0,      0,  W),
-    Item::new("Fiddler Submachine Gun Model 868",   Some("Fiddler"),            660,    0,      0,      0,  W),
-    Item::new(r#""The Pitch Gun" mc. V"#,           Some("Pitch Gun"),          440,    0,      0,      0,  C),
-    Item::new(r#""Lionclaw" mc.VIII"#,              Some("Lionclaw"),           660,    0,      0,      0,  C),
-    Item::new("9mm",                                None,                       440,    0,      0,      0,  None),
-    Item::new("PT-815 Smoke Grenade",               Some("Smoke Grenade"),      660,    0,      0,      0,  None),
-    Item::new("Green Ash Grenade",                  Some("Green Ash"),          770,    0,      0,      0,  None),
-    Item::new("12.7mm",                             None,                       550,    0,      0,      0,  None),
-];
-
-#[rustfmt::skip]
-const MPF_HEAVY_ARMS: &[Item] = &[
-    Item::new("135 Neville Anti-Tank Rifle",        Some("Anti-Tank Rifle"),    825,    0,      0,      0,  W),
-    Item::new(r#"Typhon ra.XII"#,                   Some("Typhon"),             550,    0,      24,     0,  C),
-    Item::new("20mm",                               None,                       550,    0,      0,      0,  None),
-    Item::new("Mounted Bonesaw MK.3",               Some("Mounted Bonesaw"),    550,    0,      24,     0,  W),
-    Item::new("Bonesaw MK.3",                       Some("Bonesaw"),            550,    0,      134,    0,  W),
-    Item::new("Venom c.II 35",                      Some("Venom"),              550,    0,      79,     0,  C),
-    Item::new("Bane 45",                            Some("Bane"),               825,    0,      220,    0,  C),
-    Item::new("AP/RPG",                             None,                       330,    409,    0,      0,  C),
-    Item::new("ARC/RPG",                            None,                       330,    409,    0,      0,  None),
-    Item::new("Willow's Bane Model 845",            Some("Flamethrower"),       904,    0,      165,    0,  W),
-    Item::new(r#""Molten Wind" v.II Flame Torch"#,  Some("Flamethrower"),       1014,   0,      134,    0,  C),
-    Item::new("KLG91-2 Lunaire F",                  Some("Lunaire"),            275,    0,      24,     0,  C),
-    Item::new("Tremola Grenade GPb-1",              Some("Tremola"),            825,    55,     0,      0,  None),
-    Item::new("Malone Ratcheter MK.1",              Some("Malone Ratcheter"),   550,    0,      24,     0,  W),
-    Item::new("Lamentum mm.IV",                     Some("Lamentum"),           550,    0,      24,     0,  C),
-    Item::new("Daucus isg.III",                     Some("ISG"),                550,    0,      24,     0,  C),
-    Item::new("30mm",                               None,                       440,    110,    0,      0,  None),
-    Item::new("Cremari Mortar",                     None,                       550,    0,      134,    0,  None),
-    Item::new("Mortar Flare Shell",                 None,                       330,    55,     0,      0,  None),
-    Item::new("Mortar Shrapnel Shell",              None,                       330,    79,     0,      0,  None),
-    Item::new("Mortar Shell",                       None,                       330,    189,    0,      0,  None),
-    Item::new("BF5 White Ash Flask Grenade",        Some("White Ash"),          550,    220,    0,      0,  W),
-    Item::new("Ignifist 30",                        Some("Ignifist"),           464,    189,    0,      0,  C),
-    Item::new("Mammon 91-b",                        Some("Mammon"),             550,    55,     0,      0,  None),
-    Item::new("Anti-Tank Sticky Bomb",              Some("Sticky Bomb"),        275,    275,    0,      0,  None),
-    Item::new("Cutler Foebreaker",                  Some("Foebreaker"),         550,    0,      24,     0,  W),
-    Item::new("Cutler Launcher 4",                  None,                       550,    0,      189,    0,  W),
-    Item::new("RPG Shell",                          None,                       330,    244,    0,      0,  None),
-];
-
-#[rustfmt::skip]
-const MPF_HEAVY_AMMUNITION: &[Item] = &[
-    Item::new("150mm",                              None,                       660,    0,      0,      55,     None),
-    Item::new("120mm",                              None,                       330,    79,     0,      0,      None),
-    Item::new("250mm",                              None,                       660,    0,      0,      134,    None),
-    Item::new("68mm",                               None,                       660,    660,    0,      0,      None),
-    Item::new("40mm",                               None,                       880,    660,    0,      0,      None),
-];
-
-#[rustfmt::skip]
-const MPF_UNIFORMS: &[Item] = &[
-    Item::new("Specialist's Overcoat",              None,                       550,    0,      0,      0,  W),
-    Item::new("Gunner's Breastplate",               None,                       550,    0,      0,      0,  W),
-    Item::new("Sapper Gear",                        None,                       550,    0,      0,      0,  W),
-    Item::new("Physician's Jacket",                 None,                       550,    0,      0,      0,  W),
-    Item::new("Officer's Regalia",                  None,                       550,    0,      0,      0,  W),
-    Item::new("Outrider's Mantle",                  None,                       550,    0,      0,      0,  W),
-    Item::new("Caovish Parka",                      None,                       550,    0,      0,      0,  W),
-    Item::new("Padded Boiler Suit",                 None,                       550,    0,      0,      0,  W),
-    Item::new("Velian Flak Vest",                   None,                       550,    0,      0,      0,  C),
-    Item::new("Fabri Rucksack",                     None,                       550,    0,      0,      0,  C),
-    Item::new("Grenadier's Baldric",                None,                       550,    0,      0,      0,  C),
-    Item::new("Medic Fatigues",                     None,                       550,    0,      0,      0,  C),
-    Item::new("Officialis' Attire",                 None,                       550,    0,      0,      0,  C),
-    Item::new("Legionary's Oilcoat",                None,                       550,    0,      0,      0,  C),
-    Item::new("Recon Camo",                         None,                       550,    0,      0,      0,  C),
-    Item::new("Heavy Topcoat",                      None,                       550,    0,      0,      0,  C),
-    Item::new("Tankman's Coveralls",                None,                       550,    0,      0,      0,  C),
-];
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Faction {
-    Warden,
-    Colonial,
-}
-const W: Option<Faction> = Some(Faction::Warden);
-const C: Option<Faction> = Some(Faction::Colonial);
+use crate::{
+    clipboard::SetClipboard,
+    config::Config,
+    events::{
+        EventSource,
+        TerminalEvents,
+    },
+    history::History,
+    keymap::{
+        Action,
+        Key,
+        Keymap,
+        Lookup,
+    },
+};
+
+/// How quickly a second click has to follow the first to count as a double click.
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(500);
+/// How many rows the todo-list keeps, borders included, before the boxes above it get any.
+const TODOLIST_MIN_HEIGHT: u16 = 10;
+/// How many rows of totals there's room for even when there are fewer materials to show.
+const MIN_TOTALS_ROWS: usize = 6;
 
 fn main() {
+    let catalogue: &'static Catalogue = match Catalogue::load() {
+        Ok(catalogue) => Box::leak(Box::new(catalogue)),
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(1);
+        }
+    };
+    let config: &'static Config = match Config::load() {
+        Ok(config) => Box::leak(Box::new(config)),
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(1);
+        }
+    };
+    let profiles = match Profile::load_all(catalogue) {
+        Ok(profiles) => profiles,
+        Err(err) => {
+            eprintln!("error: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        match cli::run(&args, catalogue, config, &profiles) {
+            Ok(output) => print!("{output}"),
+            Err(err) => {
+                eprintln!("error: {err}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     let panic_infos = Arc::new(Mutex::new(Vec::new()));
     panic::set_hook({
         let panic_infos = panic_infos.clone();
@@ -187,7 +189,8 @@ fn main() {
     let result = panic::catch_unwind({
         let terminal = terminal.clone();
         || {
-            run_app(terminal);
+            let mut app = App::new(catalogue, config, profiles);
+            run_app(terminal, &mut app, &mut TerminalEvents);
         }
     });
 
@@ -218,60 +221,48 @@ fn main() {
     }
 }
 
-fn run_app<B: Backend>(terminal: Arc<Mutex<Terminal<B>>>) {
-    let mut app = App::new();
+/// Draws the app and handles its events until it's quit.
+fn run_app<B: Backend>(
+    terminal: Arc<Mutex<Terminal<B>>>,
+    app: &mut App,
+    events: &mut impl EventSource,
+) {
     let mut terminal = terminal.lock().unwrap();
     let mut last_tick = Instant::now();
     let tick_rate = Duration::from_millis(250);
     loop {
         terminal
             .draw(|f| {
-                ui(f, &mut app);
+                ui(f, app);
             })
             .unwrap();
+        if events.finished() {
+            return;
+        }
 
         let timeout = tick_rate
             .checked_sub(last_tick.elapsed())
             .unwrap_or(Duration::from_secs(0));
-        if crossterm::event::poll(timeout).unwrap() {
-            if let Event::Key(key) = crossterm::event::read().unwrap() {
-                match app.selected_list {
-                    0 => match key.code {
-                        KeyCode::Right => {
-                            app.main_list.unselect();
-                            app.selected_list = 1;
-                            app.todolist.select_next();
-                        }
-                        KeyCode::Up => app.main_list.select_previous(),
-                        KeyCode::Down => app.main_list.select_next(),
-                        KeyCode::Enter => {
-                            app.add_to_todolist();
-                        }
-                        _ => {}
-                    },
-                    1 => match key.code {
-                        KeyCode::Left => {
-                            app.todolist.unselect();
-                            app.selected_list = 0;
-                            app.main_list.select_next();
-                        }
-                        KeyCode::Up => app.todolist.select_previous(),
-                        KeyCode::Down => app.todolist.select_next(),
-                        KeyCode::Enter => {
-                            app.remove_from_todolist();
-                        }
-                        _ => {}
-                    },
-                    _ => {
-                        app.selected_list = 0;
+        if let Some(event) = events.next_event(timeout) {
+            match event {
+                Event::Key(key) => {
+                    app.status = None;
+                    if app.popup.is_some() {
+                        app.on_popup_key(key.code);
+                        continue;
+                    }
+                    if app.search.is_some() {
+                        app.on_search_key(key.code);
+                        continue;
+                    }
+                    match app.on_key(key) {
+                        Some(Action::Quit) => return,
+                        Some(action) => app.perform(action),
+                        None => {}
                     }
                 }
-                match key.code {
-                    KeyCode::Char('q') => return,
-                    KeyCode::Char('w') => app.write_output(),
-                    KeyCode::Char('f') => app.swap_faction(),
-                    _ => {}
-                }
+                Event::Mouse(mouse) => app.on_mouse(mouse),
+                _ => {}
             }
         }
         if last_tick.elapsed() >= tick_rate {
@@ -282,37 +273,156 @@ fn run_app<B: Backend>(terminal: Arc<Mutex<Terminal<B>>>) {
 }
 
 fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+    let (main, status) = match *Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(f.size())
+    {
+        [main, status, ..] => (main, status),
+        _ => return,
+    };
+    match &app.status {
+        Some(Ok(message)) => f.render_widget(Paragraph::new(message.as_str()), status),
+        Some(Err(err)) => f.render_widget(
+            Paragraph::new(err.to_string()).style(Style::default().fg(Color::Red)),
+            status,
+        ),
+        None => f.render_widget(
+            Paragraph::new(key_hints(&app.config.keymap, app.selected_list == 0))
+                .style(Style::default().fg(Color::DarkGray)),
+            status,
+        ),
+    }
+
     if let [left, right, ..] = *Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(f.size())
+        .split(main)
     {
         let items: Vec<ListItem> = app
             .todolist
-            .items
+            .entries()
             .iter()
             .enumerate()
-            .map(|(n, item)| ListItem::new(format_todolist_entry(item, n, true)))
+            .map(|(n, entry)| {
+                let line = if app.todolist.target_levels {
+                    app.format_target_level(entry)
+                } else {
+                    app.config
+                        .default_template()
+                        .render_entry(entry, n, app.catalogue, true)
+                };
+                match entry.priority {
+                    Priority::Normal => ListItem::new(line),
+                    priority => ListItem::new(format!("[{}] {line}", priority.name())),
+                }
+            })
             .collect();
+        let title = if app.todolist.target_levels {
+            "Todolist (target levels)"
+        } else {
+            "Todolist"
+        };
         let items = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title("Todolist"))
+            .block(Block::default().borders(Borders::ALL).title(title))
             .highlight_style(
                 Style::default()
                     .bg(Color::DarkGray)
                     .add_modifier(Modifier::BOLD),
             );
-        if let [right_top, right_bottom, ..] = *Layout::default()
+        let settings = vec![
+            Spans::from(format!("Faction: {:?}", app.faction)),
+            Spans::from(format!("Output format: {}", app.output_format.name())),
+            Spans::from(format!(
+                "List: {}",
+                app.list_name.as_deref().unwrap_or("<unsaved>")
+            )),
+            Spans::from(format!("Notes: {}", app.notes)),
+            Spans::from(match &app.inventory {
+                Some(inventory) => format!(
+                    "Inventory: {} ({} items, {} unknown)",
+                    inventory.path,
+                    inventory.item_count(),
+                    inventory.unmatched.len()
+                ),
+                None => "Inventory: <none>".to_string(),
+            }),
+            Spans::from(format!(
+                "Profile: {}",
+                app.profile
+                    .map_or("<none>", |profile| app.profiles[profile].name.as_str())
+            )),
+            Spans::from(format!(
+                "Sort: {}",
+                match app.todolist.sort_mode() {
+                    SortMode::Category => "by category",
+                    SortMode::Manual => "manual",
+                }
+            )),
+            Spans::from(format!(
+                "Quantities: {}",
+                if app.todolist.target_levels {
+                    "crates to keep in stock"
+                } else {
+                    "queues to order"
+                }
+            )),
+        ];
+        let (totals, title): (Vec<Spans>, _) = if app.show_raw_resources {
+            let totals = app
+                .catalogue
+                .raw_resources(app.total_cost())
+                .into_iter()
+                .map(|(resource, amount)| Spans::from(format!("{resource}: {amount}")))
+                .collect();
+            (totals, "Raw resources")
+        } else {
+            let totals = app
+                .total_cost()
+                .iter()
+                .map(|(material, amount)| {
+                    Spans::from(format!(
+                        "{}: {amount} ({}📦)",
+                        material.display_name(),
+                        material.crates(amount)
+                    ))
+                })
+                .collect();
+            (totals, "Totals")
+        };
+        // The todo-list is what gets edited, so the settings and then the totals make room for
+        // it on small terminals.
+        let spare = right.height.saturating_sub(TODOLIST_MIN_HEIGHT);
+        let totals_height = (totals.len().max(MIN_TOTALS_ROWS) as u16 + 2).min(spare);
+        let settings_height = (settings.len() as u16 + 2).min(spare - totals_height);
+        let totals = fit_lines(totals, totals_height.saturating_sub(2));
+        if let [right_top, right_middle, right_bottom, ..] = *Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Length(3), Constraint::Min(10)])
+            .constraints([
+                Constraint::Length(settings_height),
+                Constraint::Length(totals_height),
+                Constraint::Min(0),
+            ])
             .split(right)
         {
             f.render_widget(
-                Paragraph::new(format!("Faction: {:?}", app.faction))
+                Paragraph::new(settings)
                     .block(Block::default().borders(Borders::ALL).title("Settings")),
                 right_top,
             );
+            app.settings_area = right_top;
+
+            f.render_widget(
+                Paragraph::new(totals).block(Block::default().borders(Borders::ALL).title(title)),
+                right_middle,
+            );
 
-            f.render_stateful_widget(items, right_bottom, &mut app.todolist.state);
+            f.render_stateful_widget(items, right_bottom, &mut app.todolist_selection.state);
+            app.todolist_area.update(
+                right_bottom,
+                app.todolist_selection.selected(),
+                app.todolist.len(),
+            );
 
             let items: Vec<ListItem> = app
                 .main_list
@@ -321,138 +431,1256 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
                 .map(|item| match item {
                     DividedListItem::Divider(name) => ListItem::new(name.clone())
                         .style(Style::default().add_modifier(Modifier::BOLD | Modifier::ITALIC)),
-                    DividedListItem::Item(item) => ListItem::new(item.name),
+                    DividedListItem::Item(item) => ListItem::new(item.name.as_str()),
                 })
                 .collect();
+            let title = match &app.search {
+                Some(query) => format!("Add (search: {query}_)"),
+                None => "Add".to_string(),
+            };
             let items = List::new(items)
-                .block(Block::default().borders(Borders::ALL).title("Add"))
+                .block(Block::default().borders(Borders::ALL).title(title))
                 .highlight_style(
                     Style::default()
                         .bg(Color::DarkGray)
                         .add_modifier(Modifier::BOLD),
                 );
             f.render_stateful_widget(items, left, &mut app.main_list.state);
+            app.main_list_area.update(
+                left,
+                app.main_list.state.selected(),
+                app.main_list.items.len(),
+            );
         }
     }
+
+    if let Some(popup) = &mut app.popup {
+        popup_ui(f, popup, app.config);
+    }
 }
 
-fn format_todolist_entry(item: &Item, n: usize, letter_width_hack: bool) -> String {
-    let format_material_amount =
-        |out: &mut String, amount: u32, name: &str, crated_amount: u32, comma: bool| {
-            if amount > 0 {
-                let comma = if comma { ", " } else { "" };
-                let crates = (amount as f32 / crated_amount as f32).ceil() as u32;
-                write!(out, "{comma}{amount} {name} ({crates}📦)").unwrap();
-                true
-            } else {
-                false | comma
+fn popup_ui<B: Backend>(f: &mut Frame<B>, popup: &mut Popup, config: &Config) {
+    let size = f.size();
+    let width = size.width.saturating_sub(4).min(60);
+    match popup {
+        Popup::Prompt {
+            prompt,
+            input,
+            error,
+        } => {
+            let area = centered_rect(width, 4, size);
+            let title = match prompt {
+                Prompt::SaveAs => "Save list as".to_string(),
+                Prompt::Notes => "Notes".to_string(),
+                Prompt::Inventory => "Import inventory from (TSV or CSV file)".to_string(),
+                Prompt::SaveProfile => "Save target levels as profile".to_string(),
+                Prompt::WriteOutput { .. } => format!(
+                    "Write output to a file in {}",
+                    config.output_file.directory.display()
+                ),
+            };
+            let text = vec![
+                Spans::from(format!("{input}_")),
+                Spans::from(error.as_deref().unwrap_or("")),
+            ];
+            f.render_widget(Clear, area);
+            f.render_widget(
+                Paragraph::new(text).block(Block::default().borders(Borders::ALL).title(title)),
+                area,
+            );
+        }
+        Popup::Open {
+            lists,
+            selection,
+            error,
+        } => {
+            let area = centered_rect(width, size.height.saturating_sub(4).min(20), size);
+            let mut items: Vec<ListItem> = lists
+                .iter()
+                .map(|name| ListItem::new(name.as_str()))
+                .collect();
+            if items.is_empty() {
+                items.push(ListItem::new("No saved lists"));
             }
-        };
-    let format_material_amounts = |item: &Item| {
-        let mut out = String::new();
-        let mut comma = false;
-        comma = format_material_amount(&mut out, item.bmats, "Bmats", 100, comma);
-        comma = format_material_amount(&mut out, item.emats, "Emats", 20, comma);
-        comma = format_material_amount(&mut out, item.rmats, "Rmats", 20, comma);
-        let _ = format_material_amount(&mut out, item.hemats, "HEmats", 20, comma);
-        out
+            let title = match error {
+                Some(error) => format!("Open list ({error})"),
+                None => "Open list".to_string(),
+            };
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .highlight_style(
+                    Style::default()
+                        .bg(Color::DarkGray)
+                        .add_modifier(Modifier::BOLD),
+                );
+            f.render_widget(Clear, area);
+            f.render_stateful_widget(list, area, &mut selection.state);
+        }
+        Popup::Help { lines, scroll } => {
+            let height = (lines.len() as u16 + 2).min(size.height.saturating_sub(2));
+            let area = centered_rect(size.width.saturating_sub(4).min(80), height, size);
+            let key_width = lines.iter().map(|(keys, _)| keys.len()).max().unwrap_or(0);
+            let text: Vec<Spans> = lines
+                .iter()
+                .map(|(keys, description)| {
+                    Spans::from(vec![
+                        Span::styled(
+                            format!("{keys:key_width$}  "),
+                            Style::default().add_modifier(Modifier::BOLD),
+                        ),
+                        Span::raw(*description),
+                    ])
+                })
+                .collect();
+            // Keeps the last line at the bottom of the popup rather than scrolling it away.
+            *scroll = (*scroll).min((lines.len() as u16 + 2).saturating_sub(height));
+            f.render_widget(Clear, area);
+            f.render_widget(
+                Paragraph::new(text).scroll((*scroll, 0)).block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title("Help (Esc to close)"),
+                ),
+                area,
+            );
+        }
+    }
+}
+
+/// A reminder of the most important keys for the focused list, for when the status line has
+/// nothing else to say.
+fn key_hints(keymap: &Keymap, add_list: bool) -> String {
+    let hints: &[(Action, &str)] = if add_list {
+        &[
+            (Action::Select, "add"),
+            (Action::Search, "search"),
+            (Action::FocusTodolist, "todo-list"),
+            (Action::Copy, "copy"),
+            (Action::Help, "help"),
+            (Action::Quit, "quit"),
+        ]
+    } else {
+        &[
+            (Action::Select, "remove"),
+            (Action::More, "more"),
+            (Action::Fewer, "fewer"),
+            (Action::FocusAddList, "add list"),
+            (Action::Undo, "undo"),
+            (Action::Help, "help"),
+        ]
     };
-    format!(
-        "{}{}・1 Queue of {}・{}",
-        char::from_u32(0x1F1E6 + n as u32).unwrap_or('X'),
-        if letter_width_hack { " " } else { "" },
-        item.short_name.unwrap_or(item.name),
-        format_material_amounts(item)
+    hints
+        .iter()
+        .map(|(action, name)| (keymap.keys(*action), name))
+        .filter(|(keys, _)| !keys.is_empty())
+        .map(|(keys, name)| format!("{keys}: {name}"))
+        .collect::<Vec<_>>()
+        .join(" · ")
+}
+
+/// Returns a rectangle of the given size in the middle of `area`.
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
     )
 }
 
 struct App {
+    catalogue: &'static Catalogue,
+    config: &'static Config,
     main_list: DividedList<&'static Item>,
-    todolist: StatefulList<&'static Item>,
+    todolist: Todolist<'static>,
+    todolist_selection: Selection,
     selected_list: usize,
     faction: Faction,
+    output_format: OutputFormat<'static>,
+    list_name: Option<String>,
+    notes: String,
+    /// Where lists are saved to and opened from.
+    lists_directory: PathBuf,
+    popup: Option<Popup>,
+    /// What's been typed into the search of the "Add" list, if it's being searched.
+    search: Option<String>,
+    /// Whether the totals are shown as the raw resources they're refined from.
+    show_raw_resources: bool,
+    inventory: Option<Inventory<'static>>,
+    profiles: Vec<Profile<'static>>,
+    /// The profile picked in the settings, indexing into `profiles`.
+    profile: Option<usize>,
+    history: History<TodolistSnapshot>,
+    /// How the last action went, for the status line. It's cleared on the next key press.
+    status: Option<Result<String, ActionError>>,
+    /// Where things were last drawn, for finding out what the mouse clicked on.
+    main_list_area: ListArea,
+    todolist_area: ListArea,
+    settings_area: Rect,
+    /// When and where the last click went, to tell double clicks apart.
+    last_click: Option<(Instant, u16, u16)>,
+    /// The keys pressed so far of a binding that takes several, like `g g`.
+    pending_keys: Vec<Key>,
+}
+
+/// Where a list was drawn and which of its items were visible.
+#[derive(Clone, Copy, Default)]
+struct ListArea {
+    /// The area inside the list's borders.
+    inner: Rect,
+    /// The index of the first visible item.
+    offset: usize,
+}
+
+impl ListArea {
+    /// Follows the scrolling of a list that was just drawn to `area`. `ListState` keeps its
+    /// offset to itself, so this works it out the same way `List` does, given that every item
+    /// is one line high.
+    fn update(&mut self, area: Rect, selected: Option<usize>, len: usize) {
+        self.inner = Block::default().borders(Borders::ALL).inner(area);
+        let height = self.inner.height as usize;
+        if len == 0 || height == 0 {
+            return;
+        }
+        let selected = match selected {
+            Some(selected) => selected.min(len - 1),
+            None => {
+                self.offset = 0;
+                return;
+            }
+        };
+        let mut offset = self.offset.min(len - 1);
+        if selected >= offset + height {
+            offset = selected + 1 - height;
+        }
+        if selected < offset {
+            offset = selected;
+        }
+        self.offset = offset;
+    }
+
+    /// Goes back to the top, like tui does when a list gets a new `ListState`.
+    fn reset(&mut self) {
+        self.offset = 0;
+    }
+
+    /// The index of the item drawn at the given position, if any. It may be past the end of the
+    /// list.
+    fn item_at(&self, column: u16, row: u16) -> Option<usize> {
+        contains(self.inner, column, row).then(|| self.offset + (row - self.inner.y) as usize)
+    }
+}
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    (area.x..area.x + area.width).contains(&column) && (area.y..area.y + area.height).contains(&row)
+}
+
+/// Why an action couldn't be carried out, which is shown on the status line. Actions that are
+/// done through a popup show their errors in the popup instead.
+#[derive(Debug)]
+enum ActionError {
+    Write { path: String, err: io::Error },
+    NothingToUndo,
+    NothingToRedo,
+    NoProfiles,
+    NoProfilePicked,
+    NoClipboard,
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Write { path, err } => write!(f, "Could not write {path}: {err}"),
+            Self::NothingToUndo => write!(f, "Nothing to undo"),
+            Self::NothingToRedo => write!(f, "Nothing to redo"),
+            Self::NoProfiles => write!(f, "There are no profiles in profiles.toml"),
+            Self::NoProfilePicked => write!(f, "Pick a profile with p first"),
+            Self::NoClipboard => write!(f, "The terminal can't set the clipboard"),
+        }
+    }
+}
+
+impl std::error::Error for ActionError {}
+
+/// Everything that undo and redo bring back. Opening a list or applying a profile also changes
+/// the list's name, notes and faction, which have to go back along with the entries, or saving
+/// after an undo would overwrite the list that was opened. Every other change to any of these is
+/// recorded too, so that undoing one change never quietly reverts another.
+#[derive(Clone)]
+struct TodolistSnapshot {
+    todolist: Todolist<'static>,
+    list_name: Option<String>,
+    notes: String,
+    faction: Faction,
+}
+
+enum Popup {
+    Prompt {
+        prompt: Prompt,
+        input: String,
+        error: Option<String>,
+    },
+    Open {
+        lists: Vec<String>,
+        selection: Selection,
+        error: Option<String>,
+    },
+    /// The keys of every action that applies to the list that had focus when it was opened.
+    Help {
+        lines: Vec<(String, &'static str)>,
+        scroll: u16,
+    },
+}
+
+enum Prompt {
+    SaveAs,
+    Notes,
+    Inventory,
+    SaveProfile,
+    /// Asks for the output's file name. If the file already exists, it's only overwritten after
+    /// confirming with a second Enter, which is remembered along with the confirmed name.
+    WriteOutput {
+        overwrite: Option<String>,
+    },
 }
 
 impl App {
-    fn new() -> Self {
+    fn new(
+        catalogue: &'static Catalogue,
+        config: &'static Config,
+        profiles: Vec<Profile<'static>>,
+    ) -> Self {
         let faction = Faction::Warden;
         Self {
-            main_list: Self::init_main_list(faction),
-            todolist: StatefulList::with_items(Vec::new()),
+            catalogue,
+            config,
+            main_list: Self::init_main_list(catalogue, faction, None),
+            todolist: Todolist::default(),
+            todolist_selection: Selection::default(),
             selected_list: 0,
             faction,
+            output_format: OutputFormat::Template(config.default_template()),
+            list_name: None,
+            notes: String::new(),
+            lists_directory: PathBuf::from(LISTS_DIR),
+            popup: None,
+            search: None,
+            show_raw_resources: false,
+            inventory: None,
+            profiles,
+            profile: None,
+            history: History::new(),
+            status: None,
+            main_list_area: ListArea::default(),
+            todolist_area: ListArea::default(),
+            settings_area: Rect::default(),
+            last_click: None,
+            pending_keys: Vec::new(),
+        }
+    }
+
+    /// Adds the key to the ones pressed so far, and returns the action they're bound to once
+    /// they make up a whole binding.
+    fn on_key(&mut self, key: KeyEvent) -> Option<Action> {
+        self.pending_keys.push(Key::from(key));
+        match self.config.keymap.lookup(&self.pending_keys) {
+            Lookup::Action(action) => {
+                self.pending_keys.clear();
+                Some(action)
+            }
+            Lookup::Pending => None,
+            // A key that doesn't carry on from the ones before it might still be a binding of
+            // its own.
+            Lookup::None if self.pending_keys.len() > 1 => {
+                self.pending_keys.clear();
+                self.on_key(key)
+            }
+            Lookup::None => {
+                self.pending_keys.clear();
+                None
+            }
+        }
+    }
+
+    /// Carries out an action on the focused list, or on the app as a whole. Actions on todo-list
+    /// entries do nothing while the "Add" list has focus.
+    fn perform(&mut self, action: Action) {
+        let add_list = self.selected_list == 0;
+        match action {
+            Action::FocusTodolist if add_list => {
+                self.main_list.unselect();
+                self.selected_list = 1;
+                self.todolist_selection.select_next(self.todolist.len());
+            }
+            Action::FocusAddList if !add_list => {
+                self.todolist_selection.unselect();
+                self.selected_list = 0;
+                self.main_list.select_next();
+            }
+            Action::FocusTodolist | Action::FocusAddList => {}
+            Action::Up if add_list => self.main_list.select_previous(),
+            Action::Up => self.todolist_selection.select_previous(self.todolist.len()),
+            Action::Down if add_list => self.main_list.select_next(),
+            Action::Down => self.todolist_selection.select_next(self.todolist.len()),
+            Action::First if add_list => self.main_list.select_first(),
+            Action::First => self.todolist_selection.select_first(self.todolist.len()),
+            Action::Last if add_list => self.main_list.select_last(),
+            Action::Last => self.todolist_selection.select_last(self.todolist.len()),
+            Action::Select if add_list => self.add_to_todolist(),
+            _ if add_list && action.is_entry_action() => {}
+            Action::Select | Action::Remove => self.remove_from_todolist(),
+            Action::More => self.change_queue_count(1),
+            Action::Fewer => self.change_queue_count(-1),
+            Action::MoveUp => self.move_entry(false),
+            Action::MoveDown => self.move_entry(true),
+            Action::Priority => self.cycle_priority(),
+            Action::Undo => self.status = Some(self.undo()),
+            Action::Redo => self.status = Some(self.redo()),
+            Action::Write => self.prompt(Prompt::WriteOutput { overwrite: None }),
+            Action::Copy => self.status = Some(self.copy_output()),
+            Action::Faction => self.status = Some(self.switch_faction()),
+            Action::Format => self.status = Some(self.next_output_format()),
+            Action::Save => self.prompt(Prompt::SaveAs),
+            Action::Notes => self.prompt(Prompt::Notes),
+            Action::Open => self.show_saved_lists(),
+            Action::Search => self.start_search(),
+            Action::RawResources => self.show_raw_resources = !self.show_raw_resources,
+            Action::Inventory => self.prompt(Prompt::Inventory),
+            Action::TargetLevels => self.toggle_target_levels(),
+            Action::NextProfile => self.status = Some(self.next_profile()),
+            Action::ApplyProfile => self.status = Some(self.apply_profile()),
+            Action::SaveProfile => self.prompt(Prompt::SaveProfile),
+            Action::Clear => self.status = Some(self.clear_todolist()),
+            Action::SortMode => self.toggle_sort_mode(),
+            Action::Help => {
+                self.popup = Some(Popup::Help {
+                    lines: self.config.keymap.help(add_list),
+                    scroll: 0,
+                });
+            }
+            // Quitting is up to the event loop.
+            Action::Quit => {}
+        }
+    }
+
+    /// Clicking selects an item in either list, and double clicking adds or removes it. The
+    /// scroll wheel moves the selection of the list under the mouse, and clicking the settings
+    /// switches factions.
+    fn on_mouse(&mut self, mouse: MouseEvent) {
+        if self.popup.is_some() {
+            return;
+        }
+        let (column, row) = (mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.status = None;
+                let now = Instant::now();
+                let double_click = matches!(
+                    self.last_click,
+                    Some((time, last_column, last_row))
+                        if (last_column, last_row) == (column, row)
+                            && now.duration_since(time) < DOUBLE_CLICK_TIME
+                );
+                // A third click starts over rather than counting as another double click.
+                self.last_click = (!double_click).then_some((now, column, row));
+
+                if let Some(index) = self.main_list_area.item_at(column, row) {
+                    if let Some(DividedListItem::Item(_)) = self.main_list.items.get(index) {
+                        self.focus_list(0);
+                        self.main_list.state.select(Some(index));
+                        if double_click {
+                            self.add_to_todolist();
+                        }
+                    }
+                } else if let Some(index) = self.todolist_area.item_at(column, row) {
+                    if index < self.todolist.len() {
+                        self.focus_list(1);
+                        self.todolist_selection.select(Some(index));
+                        if double_click {
+                            self.remove_from_todolist();
+                        }
+                    }
+                } else if contains(self.settings_area, column, row) {
+                    self.status = Some(self.switch_faction());
+                }
+            }
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+                let down = mouse.kind == MouseEventKind::ScrollDown;
+                if contains(self.main_list_area.inner, column, row) {
+                    self.focus_list(0);
+                    if down {
+                        self.main_list.select_next();
+                    } else {
+                        self.main_list.select_previous();
+                    }
+                } else if contains(self.todolist_area.inner, column, row)
+                    && !self.todolist.is_empty()
+                {
+                    self.focus_list(1);
+                    if down {
+                        self.todolist_selection.select_next(self.todolist.len());
+                    } else {
+                        self.todolist_selection.select_previous(self.todolist.len());
+                    }
+                }
+            }
+            _ => {}
         }
     }
 
-    fn init_main_list(faction: Faction) -> DividedList<&'static Item> {
+    /// Moves the focus to the "Add" list (0) or the todo-list (1), clearing the other one's
+    /// selection.
+    fn focus_list(&mut self, list: usize) {
+        if self.selected_list == list {
+            return;
+        }
+        match list {
+            0 => self.todolist_selection.unselect(),
+            _ => self.main_list.unselect(),
+        }
+        self.selected_list = list;
+    }
+
+    /// Lists the faction's items by category. When searching, only the items that match are
+    /// listed, best matches first, and categories without any matches are left out.
+    fn init_main_list(
+        catalogue: &'static Catalogue,
+        faction: Faction,
+        search: Option<&str>,
+    ) -> DividedList<&'static Item> {
         DividedList::with_items(
-            [
-                ("Small Arms", MPF_SMALL_ARMS),
-                ("Heavy Arms", MPF_HEAVY_ARMS),
-                ("Heavy Ammunition", MPF_HEAVY_AMMUNITION),
-                ("Uniforms", MPF_UNIFORMS),
-            ]
-            .into_iter()
-            .flat_map(|(name, category)| {
-                iter::once(DividedListItem::Divider(name.to_string())).chain(
-                    category
-                        .into_iter()
+            catalogue
+                .categories
+                .iter()
+                .flat_map(|category| {
+                    let mut items: Vec<(u32, &'static Item)> = category
+                        .items
+                        .iter()
                         .filter(|item| {
                             item.faction
                                 .as_ref()
                                 .map(|fac| *fac == faction)
                                 .unwrap_or(true)
                         })
-                        .map(DividedListItem::Item),
-                )
-            })
-            .collect(),
+                        .filter_map(|item| match search {
+                            Some(query) => search_score(query, item).map(|score| (score, item)),
+                            None => Some((0, item)),
+                        })
+                        .collect();
+                    items.sort_by_key(|(score, _)| Reverse(*score));
+
+                    let divider = (search.is_none() || !items.is_empty())
+                        .then(|| DividedListItem::Divider(category.name.clone()));
+                    divider.into_iter().chain(
+                        items
+                            .into_iter()
+                            .map(|(_, item)| DividedListItem::Item(item)),
+                    )
+                })
+                .collect(),
         )
     }
 
+    fn start_search(&mut self) {
+        if self.selected_list != 0 {
+            self.todolist_selection.unselect();
+            self.selected_list = 0;
+        }
+        self.search = Some(String::new());
+        self.update_search();
+    }
+
+    /// Filters the "Add" list by the search query and highlights the best match.
+    fn update_search(&mut self) {
+        let query = self.search.as_deref();
+        self.main_list = Self::init_main_list(self.catalogue, self.faction, query);
+        self.main_list_area.reset();
+        if let Some(query) = query {
+            let best = self
+                .main_list
+                .items
+                .iter()
+                .enumerate()
+                .filter_map(|(n, item)| match item {
+                    DividedListItem::Item(item) => {
+                        search_score(query, item).map(|score| (score, Reverse(n)))
+                    }
+                    DividedListItem::Divider(_) => None,
+                })
+                .max()
+                .map(|(_, Reverse(n))| n);
+            self.main_list.state.select(best);
+        }
+    }
+
+    fn end_search(&mut self) {
+        let selected = self.main_list.state.selected().and_then(|selected| {
+            match self.main_list.items.get(selected) {
+                Some(DividedListItem::Item(item)) => Some(*item),
+                _ => None,
+            }
+        });
+        self.search = None;
+        self.main_list = Self::init_main_list(self.catalogue, self.faction, None);
+        self.main_list_area.reset();
+        let position = self.main_list.items.iter().position(|item| {
+            matches!((item, selected), (DividedListItem::Item(item), Some(selected)) if item.id == selected.id)
+        });
+        self.main_list.state.select(position);
+    }
+
+    fn on_search_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Esc => self.end_search(),
+            KeyCode::Enter => self.add_to_todolist(),
+            KeyCode::Up => self.main_list.select_previous(),
+            KeyCode::Down => self.main_list.select_next(),
+            KeyCode::Backspace => {
+                if let Some(query) = &mut self.search {
+                    query.pop();
+                }
+                self.update_search();
+            }
+            KeyCode::Char(c) => {
+                if let Some(query) = &mut self.search {
+                    query.push(c);
+                }
+                self.update_search();
+            }
+            _ => {}
+        }
+    }
+
     fn add_to_todolist(&mut self) {
         if let Some(selected) = self.main_list.state.selected() {
-            if let DividedListItem::Item(item) = self.main_list.items.get(selected).unwrap() {
-                self.todolist.push(item);
-
-                self.todolist.items.sort_by(|this, other| {
-                    let find_category = |item: &'static Item| {
-                        for (n, category) in [
-                            MPF_SMALL_ARMS,
-                            MPF_HEAVY_ARMS,
-                            MPF_HEAVY_AMMUNITION,
-                            MPF_UNIFORMS,
-                        ]
-                        .into_iter()
-                        .enumerate()
-                        {
-                            if category.as_ptr_range().contains(&(item as *const Item)) {
-                                return n;
-                            }
-                        }
-                        return usize::MAX;
-                    };
+            if let DividedListItem::Item(item) = self.main_list.items[selected] {
+                // Target levels start at, and go up by, one queue's worth of crates.
+                let step = if self.todolist.target_levels {
+                    item.crates_per_queue
+                } else {
+                    1
+                };
+                match self.todolist.position(item) {
+                    Some(position) => {
+                        let count = self.todolist.entries()[position].count;
+                        self.record_change(format!(
+                            "changed {} from {} to {}",
+                            item.display_name(),
+                            self.describe_count(count),
+                            self.describe_count(count + step)
+                        ));
+                    }
+                    None => self.record_change(format!("added {}", item.display_name())),
+                }
+                self.todolist.add(item, step);
+            }
+        }
+    }
 
-                    find_category(this)
-                        .cmp(&find_category(other))
-                        .then((*this as *const Item).cmp(&(*other as *const Item)))
-                });
+    /// Moves the selected entry up or down, which switches to sorting manually.
+    fn move_entry(&mut self, down: bool) {
+        let selected = match self.todolist_selection.selected() {
+            Some(selected) => selected,
+            None => return,
+        };
+        let before = self.snapshot();
+        if let Some(target) = self.todolist.move_entry(selected, down) {
+            self.history.record(
+                format!(
+                    "moved {} {}",
+                    self.todolist.entries()[target].item.display_name(),
+                    if down { "down" } else { "up" }
+                ),
+                before,
+            );
+            self.todolist_selection.select(Some(target));
+        }
+    }
+
+    fn toggle_sort_mode(&mut self) {
+        match self.todolist.sort_mode() {
+            SortMode::Category => {
+                self.record_change("ordered the todo-list by hand".to_string());
+                self.todolist.set_sort_mode(SortMode::Manual);
+            }
+            SortMode::Manual => {
+                self.record_change("sorted the todo-list by category".to_string());
+                self.todolist.set_sort_mode(SortMode::Category);
+            }
+        }
+    }
+
+    fn toggle_target_levels(&mut self) {
+        self.record_change(if self.todolist.target_levels {
+            "switched to queues".to_string()
+        } else {
+            "switched to target levels".to_string()
+        });
+        self.todolist.target_levels = !self.todolist.target_levels;
+    }
+
+    fn cycle_priority(&mut self) {
+        if let Some(selected) = self.todolist_selection.selected() {
+            if let Some(entry) = self.todolist.get(selected) {
+                let priority = entry.priority.next();
+                self.record_change(format!(
+                    "marked {} as {}",
+                    entry.item.display_name(),
+                    priority.name().to_lowercase()
+                ));
+                let position = self.todolist.set_priority(selected, priority);
+                self.todolist_selection.select(Some(position));
             }
         }
     }
 
     fn remove_from_todolist(&mut self) {
-        if let Some(selected) = self.todolist.state.selected() {
-            let _ = self.todolist.remove(selected);
+        if let Some(selected) = self.todolist_selection.selected() {
+            if let Some(entry) = self.todolist.get(selected) {
+                self.record_change(format!("removed {}", entry.item.display_name()));
+                self.todolist.remove(selected);
+                if selected == self.todolist.len() {
+                    self.todolist_selection.select(selected.checked_sub(1));
+                }
+            }
+        }
+    }
+
+    fn change_queue_count(&mut self, delta: i32) {
+        if let Some(selected) = self.todolist_selection.selected() {
+            if let Some(entry) = self.todolist.get(selected) {
+                let count = if delta < 0 {
+                    entry.count.saturating_sub(delta.unsigned_abs())
+                } else {
+                    entry.count.saturating_add(delta.unsigned_abs())
+                }
+                .max(1);
+                if count != entry.count {
+                    self.record_change(format!(
+                        "changed {} from {} to {}",
+                        entry.item.display_name(),
+                        self.describe_count(entry.count),
+                        self.describe_count(count)
+                    ));
+                    self.todolist.set_count(selected, count);
+                }
+            }
+        }
+    }
+
+    fn clear_todolist(&mut self) -> Result<String, ActionError> {
+        if self.todolist.is_empty() {
+            return Ok("The todo-list is already empty".to_string());
+        }
+        self.record_change("cleared the todo-list".to_string());
+        self.todolist.clear();
+        self.todolist_selection.select(None);
+        Ok("Cleared the todo-list, u brings it back".to_string())
+    }
+
+    /// Describes an entry's count, which is in queues or crates depending on the mode.
+    fn describe_count(&self, count: u32) -> String {
+        if self.todolist.target_levels {
+            format!("{count} crates")
+        } else {
+            format_queue_count(count)
+        }
+    }
+
+    fn snapshot(&self) -> TodolistSnapshot {
+        TodolistSnapshot {
+            todolist: self.todolist.clone(),
+            list_name: self.list_name.clone(),
+            notes: self.notes.clone(),
+            faction: self.faction,
+        }
+    }
+
+    /// Remembers the todo-list as it is before a change, so the change can be undone.
+    fn record_change(&mut self, description: String) {
+        let snapshot = self.snapshot();
+        self.history.record(description, snapshot);
+    }
+
+    fn restore(&mut self, snapshot: TodolistSnapshot) {
+        self.todolist = snapshot.todolist;
+        self.list_name = snapshot.list_name;
+        self.notes = snapshot.notes;
+        if self.faction != snapshot.faction {
+            self.swap_faction();
+        }
+        let last = self.todolist.len().checked_sub(1);
+        if let Some(selected) = self.todolist_selection.selected() {
+            self.todolist_selection
+                .select(last.map(|last| selected.min(last)));
+        }
+    }
+
+    fn undo(&mut self) -> Result<String, ActionError> {
+        let (description, before) = self
+            .history
+            .undo(self.snapshot())
+            .ok_or(ActionError::NothingToUndo)?;
+        self.restore(before);
+        Ok(format!("Undone: {description}"))
+    }
+
+    fn redo(&mut self) -> Result<String, ActionError> {
+        let (description, after) = self
+            .history
+            .redo(self.snapshot())
+            .ok_or(ActionError::NothingToRedo)?;
+        self.restore(after);
+        Ok(format!("Redone: {description}"))
+    }
+
+    /// Writes the output to a file in the configured output directory.
+    fn write_output(&self, file_name: &str) -> Result<String, ActionError> {
+        let path = self.config.output_file.path(file_name);
+        let error = |err| ActionError::Write {
+            path: path.display().to_string(),
+            err,
+        };
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(error)?;
+        }
+        fs::write(&path, self.output()).map_err(error)?;
+        Ok(format!(
+            "Wrote {} to {}",
+            describe_entry_count(self.order().len()),
+            path.display()
+        ))
+    }
+
+    /// Sends the output to the clipboard, or asks for a file to write it to if that fails.
+    /// Terminals without OSC 52 support ignore it without an error, so there's no telling
+    /// whether it actually arrived.
+    fn copy_output(&mut self) -> Result<String, ActionError> {
+        if crossterm::execute!(io::stdout(), SetClipboard(&self.output())).is_err() {
+            self.prompt(Prompt::WriteOutput { overwrite: None });
+            return Err(ActionError::NoClipboard);
+        }
+        Ok(format!(
+            "Sent {} to the clipboard (needs OSC 52 support)",
+            describe_entry_count(self.order().len())
+        ))
+    }
+
+    fn next_output_format(&mut self) -> Result<String, ActionError> {
+        self.output_format = self.output_format.next(&self.config.templates);
+        Ok(format!("Output format: {}", self.output_format.name()))
+    }
+
+    fn output(&self) -> String {
+        format_todolist(&self.order(), self.catalogue, self.output_format)
+    }
+
+    fn total_cost(&self) -> Cost {
+        self.todolist.total_cost(self.inventory.as_ref())
+    }
+
+    fn order(&self) -> Vec<TodolistEntry<'static>> {
+        self.todolist.order(self.inventory.as_ref())
+    }
+
+    fn format_target_level(&self, entry: &TodolistEntry) -> String {
+        let held = self
+            .inventory
+            .as_ref()
+            .map_or(0, |inventory| inventory.crates_of(entry.item));
+        let order = shortfall(std::slice::from_ref(entry), self.inventory.as_ref());
+        let queues = order.first().map_or(0, |entry| entry.count);
+        format!(
+            "{}・{held}/{} crates・{}",
+            entry.item.display_name(),
+            entry.count,
+            if queues == 0 {
+                "stocked".to_string()
+            } else {
+                format_queue_count(queues)
+            }
+        )
+    }
+
+    fn prompt(&mut self, prompt: Prompt) {
+        let input = match prompt {
+            Prompt::SaveAs => self.list_name.clone().unwrap_or_default(),
+            Prompt::Notes => self.notes.clone(),
+            Prompt::Inventory => self
+                .inventory
+                .as_ref()
+                .map(|inventory| inventory.path.clone())
+                .unwrap_or_default(),
+            Prompt::SaveProfile => self
+                .profile
+                .map(|profile| self.profiles[profile].name.clone())
+                .unwrap_or_default(),
+            Prompt::WriteOutput { .. } => self.config.output_file.file_name(
+                self.faction,
+                self.list_name.as_deref(),
+                SystemTime::now(),
+            ),
+        };
+        self.popup = Some(Popup::Prompt {
+            prompt,
+            input,
+            error: None,
+        });
+    }
+
+    fn show_saved_lists(&mut self) {
+        let (names, error) = match save::saved_lists(&self.lists_directory) {
+            Ok(names) => (names, None),
+            Err(err) => (Vec::new(), Some(err.to_string())),
+        };
+        let mut selection = Selection::default();
+        if !names.is_empty() {
+            selection.select(Some(0));
+        }
+        self.popup = Some(Popup::Open {
+            lists: names,
+            selection,
+            error,
+        });
+    }
+
+    fn on_popup_key(&mut self, code: KeyCode) {
+        self.popup = match self.popup.take() {
+            Some(Popup::Prompt {
+                prompt,
+                mut input,
+                error,
+            }) => match code {
+                KeyCode::Esc => None,
+                KeyCode::Enter => match prompt {
+                    Prompt::SaveAs if input.trim().is_empty() => Some(Popup::Prompt {
+                        prompt,
+                        input,
+                        error: Some("The list needs a name".to_string()),
+                    }),
+                    Prompt::SaveAs => match self.save_list(input.trim().to_string()) {
+                        Ok(message) => {
+                            self.status = Some(Ok(message));
+                            None
+                        }
+                        Err(err) => Some(Popup::Prompt {
+                            prompt,
+                            input,
+                            error: Some(err.to_string()),
+                        }),
+                    },
+                    Prompt::Notes => {
+                        if input != self.notes {
+                            self.record_change("edited the notes".to_string());
+                        }
+                        self.notes = input;
+                        self.status = Some(Ok("Updated the notes".to_string()));
+                        None
+                    }
+                    Prompt::SaveProfile if !self.todolist.target_levels => Some(Popup::Prompt {
+                        prompt,
+                        input,
+                        error: Some("Switch the todo-list to target levels first".to_string()),
+                    }),
+                    Prompt::SaveProfile if input.trim().is_empty() => Some(Popup::Prompt {
+                        prompt,
+                        input,
+                        error: Some("The profile needs a name".to_string()),
+                    }),
+                    Prompt::SaveProfile => match self.save_profile(input.trim().to_string()) {
+                        Ok(message) => {
+                            self.status = Some(Ok(message));
+                            None
+                        }
+                        Err(err) => Some(Popup::Prompt {
+                            prompt,
+                            input,
+                            error: Some(err.to_string()),
+                        }),
+                    },
+                    Prompt::WriteOutput { .. } if input.trim().is_empty() => Some(Popup::Prompt {
+                        prompt,
+                        input,
+                        error: Some("The file needs a name".to_string()),
+                    }),
+                    Prompt::WriteOutput { overwrite } => {
+                        let file_name = input.trim();
+                        let path = self.config.output_file.path(file_name);
+                        if path.exists() && overwrite.as_deref() != Some(file_name) {
+                            Some(Popup::Prompt {
+                                prompt: Prompt::WriteOutput {
+                                    overwrite: Some(file_name.to_string()),
+                                },
+                                error: Some(format!(
+                                    "{} already exists, press Enter again to overwrite it",
+                                    path.display()
+                                )),
+                                input,
+                            })
+                        } else {
+                            match self.write_output(file_name) {
+                                Ok(message) => {
+                                    self.status = Some(Ok(message));
+                                    None
+                                }
+                                Err(err) => Some(Popup::Prompt {
+                                    prompt: Prompt::WriteOutput { overwrite },
+                                    error: Some(err.to_string()),
+                                    input,
+                                }),
+                            }
+                        }
+                    }
+                    Prompt::Inventory => match Inventory::load(input.trim(), self.catalogue) {
+                        Ok(inventory) => {
+                            let mut message = format!(
+                                "Imported {} items from {}",
+                                inventory.item_count(),
+                                inventory.path
+                            );
+                            if !inventory.unmatched.is_empty() {
+                                message += &format!(
+                                    ", {} names weren't in the catalogue",
+                                    inventory.unmatched.len()
+                                );
+                            }
+                            self.inventory = Some(inventory);
+                            self.status = Some(Ok(message));
+                            None
+                        }
+                        Err(err) => Some(Popup::Prompt {
+                            prompt,
+                            input,
+                            error: Some(err.to_string()),
+                        }),
+                    },
+                },
+                KeyCode::Backspace => {
+                    input.pop();
+                    Some(Popup::Prompt {
+                        prompt,
+                        input,
+                        error,
+                    })
+                }
+                KeyCode::Char(c) => {
+                    input.push(c);
+                    Some(Popup::Prompt {
+                        prompt,
+                        input,
+                        error,
+                    })
+                }
+                _ => Some(Popup::Prompt {
+                    prompt,
+                    input,
+                    error,
+                }),
+            },
+            Some(Popup::Open {
+                lists,
+                mut selection,
+                error,
+            }) => match code {
+                KeyCode::Esc => None,
+                KeyCode::Up => {
+                    selection.select_previous(lists.len());
+                    Some(Popup::Open {
+                        lists,
+                        selection,
+                        error,
+                    })
+                }
+                KeyCode::Down => {
+                    selection.select_next(lists.len());
+                    Some(Popup::Open {
+                        lists,
+                        selection,
+                        error,
+                    })
+                }
+                KeyCode::Enter => {
+                    let selected = selection
+                        .selected()
+                        .and_then(|selected| lists.get(selected));
+                    match selected.map(|name| self.open_list(name)) {
+                        Some(Ok(message)) => {
+                            self.status = Some(Ok(message));
+                            None
+                        }
+                        Some(Err(err)) => Some(Popup::Open {
+                            lists,
+                            selection,
+                            error: Some(err.to_string()),
+                        }),
+                        None => Some(Popup::Open {
+                            lists,
+                            selection,
+                            error,
+                        }),
+                    }
+                }
+                _ => Some(Popup::Open {
+                    lists,
+                    selection,
+                    error,
+                }),
+            },
+            Some(Popup::Help { lines, scroll }) => match code {
+                KeyCode::Esc | KeyCode::Enter | KeyCode::Char('?' | 'q') => None,
+                KeyCode::Up => Some(Popup::Help {
+                    lines,
+                    scroll: scroll.saturating_sub(1),
+                }),
+                KeyCode::Down => Some(Popup::Help {
+                    lines,
+                    scroll: scroll + 1,
+                }),
+                _ => Some(Popup::Help { lines, scroll }),
+            },
+            None => None,
+        };
+    }
+
+    fn save_list(&mut self, name: String) -> Result<String, SaveError> {
+        let entries = self
+            .todolist
+            .entries()
+            .iter()
+            .map(|entry| SavedEntry {
+                item: entry.item.id.clone(),
+                count: entry.count,
+                priority: entry.priority,
+            })
+            .collect();
+        let path = SavedList::new(
+            name.clone(),
+            self.faction,
+            self.notes.clone(),
+            self.todolist.target_levels,
+            self.todolist.sort_mode(),
+            entries,
+        )
+        .save(&self.lists_directory)?;
+        if self.list_name.as_deref() != Some(name.as_str()) {
+            self.record_change(format!("named the list {name}"));
         }
+        self.list_name = Some(name);
+        Ok(format!("Saved the list to {}", path.display()))
     }
 
-    fn write_output(&self) {
-        let mut output = String::new();
-        for (n, item) in self.todolist.items.iter().enumerate() {
-            writeln!(output, "{}", format_todolist_entry(item, n, false)).unwrap();
+    fn open_list(&mut self, name: &str) -> Result<String, SaveError> {
+        let list = SavedList::load(&self.lists_directory, name)?;
+        let entries = list
+            .entries
+            .iter()
+            .map(|entry| {
+                list.find_item(self.catalogue, &entry.item)
+                    .map(|item| TodolistEntry {
+                        item,
+                        count: entry.count.max(1),
+                        priority: entry.priority,
+                    })
+                    .ok_or_else(|| SaveError::UnknownItem(entry.item.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.record_change(format!("opened list {}", list.name));
+        let message = format!(
+            "Opened {} with {}",
+            list.name,
+            describe_entry_count(entries.len())
+        );
+        self.todolist = Todolist::new(entries, list.target_levels, list.sort);
+        self.todolist_selection = Selection::default();
+        self.todolist_area.reset();
+        self.list_name = Some(list.name);
+        self.notes = list.notes;
+        if self.faction != list.faction {
+            self.swap_faction();
+        }
+        Ok(message)
+    }
+
+    fn next_profile(&mut self) -> Result<String, ActionError> {
+        if self.profiles.is_empty() {
+            return Err(ActionError::NoProfiles);
+        }
+        self.profile = match self.profile {
+            None => Some(0),
+            Some(profile) if profile + 1 < self.profiles.len() => Some(profile + 1),
+            _ => None,
+        };
+        Ok(match self.profile {
+            Some(profile) => format!(
+                "Picked profile {}, g applies it",
+                self.profiles[profile].name
+            ),
+            None => "No profile picked".to_string(),
+        })
+    }
+
+    /// Replaces the todo-list with the picked profile's target levels. If an inventory has been
+    /// imported, what gets ordered is only what it's short of.
+    fn apply_profile(&mut self) -> Result<String, ActionError> {
+        let profile = match self.profile {
+            Some(profile) => &self.profiles[profile],
+            None => return Err(ActionError::NoProfilePicked),
+        };
+        let message = format!("Applied profile {}", profile.name);
+        let faction = profile.faction;
+        let entries = profile.entries();
+        self.record_change(format!("applied profile {}", profile.name));
+        self.todolist = Todolist::new(entries, true, self.todolist.sort_mode());
+        self.todolist_selection = Selection::default();
+        self.todolist_area.reset();
+        self.list_name = None;
+        if faction.is_some_and(|faction| faction != self.faction) {
+            self.swap_faction();
         }
-        std::fs::write("output.txt", output.as_bytes()).unwrap();
+        Ok(message)
+    }
+
+    /// Saves the todo-list's target levels as a profile, replacing any profile with the same
+    /// name.
+    fn save_profile(&mut self, name: String) -> Result<String, ProfileError> {
+        let profile = Profile {
+            name,
+            faction: Some(self.faction),
+            targets: self
+                .todolist
+                .entries()
+                .iter()
+                .map(|entry| (entry.item, entry.count))
+                .collect(),
+        };
+        let position = match self
+            .profiles
+            .iter()
+            .position(|other| other.name == profile.name)
+        {
+            Some(position) => {
+                self.profiles[position] = profile;
+                position
+            }
+            None => {
+                self.profiles.push(profile);
+                self.profiles.len() - 1
+            }
+        };
+        self.profile = Some(position);
+        Profile::save_all(&self.profiles)?;
+        Ok(format!("Saved profile {}", self.profiles[position].name))
+    }
+
+    fn switch_faction(&mut self) -> Result<String, ActionError> {
+        let before = self.snapshot();
+        self.swap_faction();
+        self.history
+            .record(format!("switched to {:?} items", self.faction), before);
+        Ok(format!("Switched to {:?} items", self.faction))
     }
 
     fn swap_faction(&mut self) {
@@ -460,66 +1688,106 @@ impl App {
             Faction::Warden => Faction::Colonial,
             Faction::Colonial => Faction::Warden,
         };
-        self.main_list = Self::init_main_list(self.faction);
+        if self.search.is_some() {
+            self.update_search();
+        } else {
+            self.main_list = Self::init_main_list(self.catalogue, self.faction, None);
+            self.main_list_area.reset();
+        }
+    }
+}
+
+/// Cuts lines down to the number of rows there's room for, ending with how many were left out so
+/// that nothing goes missing without a word.
+fn fit_lines(mut lines: Vec<Spans>, rows: u16) -> Vec<Spans> {
+    let rows = rows as usize;
+    if lines.len() > rows && rows > 0 {
+        let hidden = lines.len() - (rows - 1);
+        lines.truncate(rows - 1);
+        lines.push(Spans::from(format!("…and {hidden} more")));
     }
+    lines
 }
 
-struct StatefulList<T> {
+/// Describes how many entries something holds, like "3 entries".
+fn describe_entry_count(count: usize) -> String {
+    match count {
+        1 => "1 entry".to_string(),
+        count => format!("{count} entries"),
+    }
+}
+
+fn search_score(query: &str, item: &Item) -> Option<u32> {
+    let name = fuzzy_score(query, &item.name);
+    let short_name = item
+        .short_name
+        .as_deref()
+        .and_then(|short_name| fuzzy_score(query, short_name));
+    name.max(short_name)
+}
+
+/// Which item of a list is selected, for lists that keep their items elsewhere.
+#[derive(Default)]
+struct Selection {
     state: ListState,
     last_pos: Option<usize>,
-    items: Vec<T>,
 }
 
-impl<T> StatefulList<T> {
-    fn with_items(items: Vec<T>) -> Self {
-        Self {
-            state: ListState::default(),
-            last_pos: None,
-            items,
-        }
+impl Selection {
+    fn selected(&self) -> Option<usize> {
+        self.state.selected()
     }
 
-    fn push(&mut self, item: T) {
-        self.items.push(item);
+    fn select(&mut self, index: Option<usize>) {
+        self.state.select(index);
     }
 
-    fn remove(&mut self, idx: usize) -> T {
-        if let Some(selected) = self.state.selected() {
-            if selected == self.items.len() - 1 {
-                self.state.select(selected.checked_sub(1));
-            }
+    fn select_next(&mut self, len: usize) {
+        if len == 0 {
+            return;
         }
-        self.items.remove(idx)
-    }
-
-    fn select_next(&mut self) {
         let i = match self.state.selected() {
             Some(i) => {
-                if i >= self.items.len() - 1 {
+                if i >= len - 1 {
                     0
                 } else {
                     i + 1
                 }
             }
-            None => self.last_pos.unwrap_or(0),
+            None => self.last_pos.unwrap_or(0).min(len - 1),
         };
         self.state.select(Some(i));
     }
 
-    fn select_previous(&mut self) {
+    fn select_previous(&mut self, len: usize) {
+        if len == 0 {
+            return;
+        }
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.items.len() - 1
+                    len - 1
                 } else {
                     i - 1
                 }
             }
-            None => self.last_pos.unwrap_or(0),
+            None => self.last_pos.unwrap_or(0).min(len - 1),
         };
         self.state.select(Some(i));
     }
 
+    fn select_first(&mut self, len: usize) {
+        if len > 0 {
+            self.state.select(Some(0));
+        }
+    }
+
+    fn select_last(&mut self, len: usize) {
+        if len > 0 {
+            self.state.select(Some(len - 1));
+        }
+    }
+
     fn unselect(&mut self) {
         self.last_pos = self.state.selected();
         self.state.select(None);
@@ -547,6 +1815,9 @@ impl<T> DividedList<T> {
     }
 
     fn select_next(&mut self) {
+        if !self.has_items() {
+            return;
+        }
         let i = match self.state.selected() {
             Some(mut i) => loop {
                 i = if i >= self.items.len() - 1 { 0 } else { i + 1 };
@@ -554,12 +1825,18 @@ impl<T> DividedList<T> {
                     break i;
                 }
             },
-            None => self.last_pos.unwrap_or(0),
+            None => match self.last_pos {
+                Some(last_pos) => last_pos,
+                None => return self.select_first(),
+            },
         };
         self.state.select(Some(i));
     }
 
     fn select_previous(&mut self) {
+        if !self.has_items() {
+            return;
+        }
         let i = match self.state.selected() {
             Some(mut i) => loop {
                 i = if i == 0 { self.items.len() - 1 } else { i - 1 };
@@ -567,69 +1844,45 @@ impl<T> DividedList<T> {
                     break i;
                 }
             },
-            None => self.last_pos.unwrap_or(0),
+            None => match self.last_pos {
+                Some(last_pos) => last_pos,
+                None => return self.select_first(),
+            },
         };
         self.state.select(Some(i));
     }
 
-    fn unselect(&mut self) {
-        self.last_pos = self.state.selected();
-        self.state.select(None);
+    fn select_first(&mut self) {
+        let first = self
+            .items
+            .iter()
+            .position(|item| matches!(item, DividedListItem::Item(_)));
+        if first.is_some() {
+            self.state.select(first);
+        }
     }
-}
 
-struct Item {
-    name: &'static str,
-    short_name: Option<&'static str>,
-    bmats: u32,
-    emats: u32,
-    rmats: u32,
-    hemats: u32,
-    #[allow(dead_code)]
-    useless: bool,
-    faction: Option<Faction>,
-}
-
-impl Item {
-    const fn new(
-        name: &'static str,
-        short_name: Option<&'static str>,
-        bmats: u32,
-        emats: u32,
-        rmats: u32,
-        hemats: u32,
-        faction: Option<Faction>,
-    ) -> Self {
-        Self {
-            name,
-            short_name,
-            bmats,
-            emats,
-            rmats,
-            hemats,
-            useless: false,
-            faction,
+    fn select_last(&mut self) {
+        let last = self
+            .items
+            .iter()
+            .rposition(|item| matches!(item, DividedListItem::Item(_)));
+        if last.is_some() {
+            self.state.select(last);
         }
     }
 
-    const fn new_useless(
-        name: &'static str,
-        short_name: Option<&'static str>,
-        bmats: u32,
-        emats: u32,
-        rmats: u32,
-        hemats: u32,
-        faction: Option<Faction>,
-    ) -> Self {
-        Self {
-            name,
-            short_name,
-            bmats,
-            emats,
-            rmats,
-            hemats,
-            useless: true,
-            faction,
-        }
+    fn has_items(&self) -> bool {
+        self.items
+            .iter()
+            .any(|item| matches!(item, DividedListItem::Item(_)))
+    }
+
+    fn unselect(&mut self) {
+        self.last_pos = self.state.selected();
+        self.state.select(None);
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,103 @@
+/// Everything the MPF can charge for an order.
+///
+/// Adding a material here is all that's needed for the catalogue, templates and output formats
+/// to support it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Material {
+    Bmats,
+    Emats,
+    Rmats,
+    Hemats,
+    Pcmats,
+    Steel,
+    Asmats1,
+    Asmats2,
+    Asmats3,
+    Asmats4,
+    Asmats5,
+    RareAlloys,
+    ThermalShielding,
+    NavalHullSegments,
+}
+
+impl Material {
+    /// Every material, in the order they're listed in.
+    pub const ALL: [Self; 14] = [
+        Self::Bmats,
+        Self::Emats,
+        Self::Rmats,
+        Self::Hemats,
+        Self::Pcmats,
+        Self::Steel,
+        Self::Asmats1,
+        Self::Asmats2,
+        Self::Asmats3,
+        Self::Asmats4,
+        Self::Asmats5,
+        Self::RareAlloys,
+        Self::ThermalShielding,
+        Self::NavalHullSegments,
+    ];
+
+    /// The name the material goes by in the catalogue and in template placeholders.
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Bmats => "bmats",
+            Self::Emats => "emats",
+            Self::Rmats => "rmats",
+            Self::Hemats => "hemats",
+            Self::Pcmats => "pcmats",
+            Self::Steel => "steel",
+            Self::Asmats1 => "asmats1",
+            Self::Asmats2 => "asmats2",
+            Self::Asmats3 => "asmats3",
+            Self::Asmats4 => "asmats4",
+            Self::Asmats5 => "asmats5",
+            Self::RareAlloys => "rare_alloys",
+            Self::ThermalShielding => "thermal_shielding",
+            Self::NavalHullSegments => "naval_hull_segments",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|material| material.key() == key)
+    }
+
+    pub fn display_name(self) -> &'static str {
+        match self {
+            Self::Bmats => "Bmats",
+            Self::Emats => "Emats",
+            Self::Rmats => "Rmats",
+            Self::Hemats => "HEmats",
+            Self::Pcmats => "PCmats",
+            Self::Steel => "Steel",
+            Self::Asmats1 => "Asmats I",
+            Self::Asmats2 => "Asmats II",
+            Self::Asmats3 => "Asmats III",
+            Self::Asmats4 => "Asmats IV",
+            Self::Asmats5 => "Asmats V",
+            Self::RareAlloys => "Rare Alloys",
+            Self::ThermalShielding => "Thermal Shielding",
+            Self::NavalHullSegments => "Naval Hull Segments",
+        }
+    }
+
+    /// How much of the material fits in one crate.
+    pub fn crate_size(self) -> u32 {
+        match self {
+            Self::Bmats => 100,
+            Self::NavalHullSegments => 5,
+            _ => 20,
+        }
+    }
+
+    /// How many crates it takes to carry the given amount of the material.
+    pub fn crates(self, amount: u32) -> u32 {
+        amount.div_ceil(self.crate_size())
+    }
+
+    /// Where the material is kept in a `Cost`.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
@@ -0,0 +1,214 @@
+use std::fmt::Write;
+
+use crate::{
+    catalogue::{
+        Catalogue,
+        Cost,
+    },
+    template::Template,
+    todolist::{
+        Priority,
+        TodolistEntry,
+    },
+};
+
+/// How the todo-list is written out when it's saved to a file or copied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat<'a> {
+    /// One line per entry, following the built-in template or one from the config file.
+    Template(&'a Template),
+    /// Markdown with a header per category and a checkbox per entry.
+    Discord,
+    /// Plain ASCII, for the in-game chat which can't show emoji.
+    Plain,
+}
+
+impl<'a> OutputFormat<'a> {
+    /// Cycles through the templates first, and then the other formats. The first template is
+    /// the default one.
+    pub fn next(self, templates: &'a [Template]) -> Self {
+        match self {
+            Self::Template(template) => {
+                let position = templates
+                    .iter()
+                    .position(|other| std::ptr::eq(other, template));
+                match position.and_then(|position| templates.get(position + 1)) {
+                    Some(next) => Self::Template(next),
+                    None => Self::Discord,
+                }
+            }
+            Self::Discord => Self::Plain,
+            Self::Plain => Self::Template(&templates[0]),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Template(template) => &template.name,
+            Self::Discord => "Discord",
+            Self::Plain => "Plain",
+        }
+    }
+}
+
+pub fn format_todolist(
+    entries: &[TodolistEntry],
+    catalogue: &Catalogue,
+    format: OutputFormat,
+) -> String {
+    let mut output = String::new();
+    let total = entries.iter().map(TodolistEntry::cost).sum();
+
+    // Entries are only grouped by priority once any of them has a priority other than normal,
+    // so that lists which don't use priorities come out the same as before.
+    let groups: Vec<(Option<Priority>, Vec<&TodolistEntry>)> = if entries
+        .iter()
+        .all(|entry| entry.priority == Priority::Normal)
+    {
+        vec![(None, entries.iter().collect())]
+    } else {
+        Priority::ALL
+            .into_iter()
+            .map(|priority| {
+                let group = entries
+                    .iter()
+                    .filter(|entry| entry.priority == priority)
+                    .collect();
+                (Some(priority), group)
+            })
+            .filter(|(_, group): &(_, Vec<_>)| !group.is_empty())
+            .collect()
+    };
+
+    let mut n = 0;
+    for (i, (priority, group)) in groups.iter().enumerate() {
+        if let Some(priority) = priority {
+            if i > 0 {
+                writeln!(output).unwrap();
+            }
+            match format {
+                OutputFormat::Template(_) | OutputFormat::Plain => {
+                    writeln!(output, "{}:", priority.name()).unwrap();
+                }
+                OutputFormat::Discord => writeln!(output, "## {}", priority.name()).unwrap(),
+            }
+        }
+
+        match format {
+            OutputFormat::Template(template) => {
+                for entry in group {
+                    writeln!(
+                        output,
+                        "{}",
+                        template.render_entry(entry, n, catalogue, false)
+                    )
+                    .unwrap();
+                    n += 1;
+                }
+            }
+            OutputFormat::Discord => {
+                let mut current_category = None;
+                for entry in group {
+                    let category = catalogue
+                        .category_of(entry.item)
+                        .map_or("Other", |category| category.name.as_str());
+                    if current_category != Some(category) {
+                        if current_category.is_some() {
+                            writeln!(output).unwrap();
+                        }
+                        writeln!(output, "### {category}").unwrap();
+                        current_category = Some(category);
+                    }
+                    writeln!(
+                        output,
+                        "- [ ] **{}** × {}{}・{}",
+                        entry.item.display_name(),
+                        format_queue_count(entry.count),
+                        format_units(entry),
+                        format_material_amounts(entry.cost(), true)
+                    )
+                    .unwrap();
+                }
+            }
+            OutputFormat::Plain => {
+                for entry in group {
+                    n += 1;
+                    writeln!(
+                        output,
+                        "{}) {} of {}{} - {}",
+                        n,
+                        format_queue_count(entry.count),
+                        entry.item.display_name(),
+                        format_units(entry),
+                        format_material_amounts(entry.cost(), false)
+                    )
+                    .unwrap();
+                }
+            }
+        }
+    }
+
+    if !entries.is_empty() {
+        match format {
+            OutputFormat::Template(template) => {
+                if let Some(total) = template.render_total(total) {
+                    writeln!(output, "{total}").unwrap();
+                }
+            }
+            OutputFormat::Discord => writeln!(
+                output,
+                "\n**Total:** {}",
+                format_material_amounts(total, true)
+            )
+            .unwrap(),
+            OutputFormat::Plain => {
+                writeln!(output, "Total: {}", format_material_amounts(total, false)).unwrap();
+            }
+        }
+    }
+    output
+}
+
+/// Lists the raw resources it takes to refine the list's materials, one per line.
+pub fn format_raw_resources(entries: &[TodolistEntry], catalogue: &Catalogue) -> String {
+    let total = entries.iter().map(TodolistEntry::cost).sum();
+    let mut output = String::new();
+    for (resource, amount) in catalogue.raw_resources(total) {
+        writeln!(output, "{amount} {resource}").unwrap();
+    }
+    output
+}
+
+pub fn format_queue_count(queues: u32) -> String {
+    if queues == 1 {
+        "1 Queue".to_string()
+    } else {
+        format!("{queues} Queues")
+    }
+}
+
+/// Spells out how many units an entry makes for items that come several to a crate, like
+/// vehicles. For everything else a queue count says enough on its own.
+fn format_units(entry: &TodolistEntry) -> String {
+    if entry.item.units_per_crate > 1 {
+        format!(" ({} units)", entry.units())
+    } else {
+        String::new()
+    }
+}
+
+/// Lists every material the cost includes, with the crate counts marked by an emoji or in words.
+pub fn format_material_amounts(cost: Cost, emoji: bool) -> String {
+    let mut out = String::new();
+    for (material, amount) in cost.iter() {
+        let comma = if out.is_empty() { "" } else { ", " };
+        let name = material.display_name();
+        let crates = material.crates(amount);
+        if emoji {
+            write!(out, "{comma}{amount} {name} ({crates}📦)").unwrap();
+        } else {
+            write!(out, "{comma}{amount} {name} ({crates} crates)").unwrap();
+        }
+    }
+    out
+}
@@ -0,0 +1,137 @@
+use std::{
+    fmt,
+    path::PathBuf,
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+use fstlg::{
+    catalogue::Faction,
+    save::sanitize_file_name,
+};
+
+/// Where `w` suggests writing the output to, set by the `[output]` table of `config.toml`.
+///
+/// The file name can have the placeholders `{faction}`, `{list}` for the name of the saved list,
+/// `{date}` and `{time}`, so that every list gets a file of its own.
+pub struct OutputFile {
+    pub directory: PathBuf,
+    file_name: String,
+}
+
+impl OutputFile {
+    pub fn new(file_name: &str) -> Result<Self, OutputFileError> {
+        if file_name.trim().is_empty() {
+            return Err(OutputFileError::Empty);
+        }
+        if file_name.contains(['/', '\\']) {
+            return Err(OutputFileError::Separator);
+        }
+        let mut rest = file_name;
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| OutputFileError::Unclosed(rest[start + 1..].to_string()))?;
+            let name = &rest[start + 1..start + end];
+            if !PLACEHOLDERS.contains(&name) {
+                return Err(OutputFileError::UnknownPlaceholder(name.to_string()));
+            }
+            rest = &rest[start + end + 1..];
+        }
+        Ok(Self {
+            file_name: file_name.to_string(),
+            ..Self::default()
+        })
+    }
+
+    /// Fills in the file name's placeholders. Times are in UTC.
+    pub fn file_name(&self, faction: Faction, list: Option<&str>, now: SystemTime) -> String {
+        let (date, time) = timestamp(now);
+        self.file_name
+            .replace("{faction}", &format!("{faction:?}"))
+            .replace("{list}", &sanitize_file_name(list.unwrap_or("todo-list")))
+            .replace("{date}", &date)
+            .replace("{time}", &time)
+    }
+
+    pub fn path(&self, file_name: &str) -> PathBuf {
+        self.directory.join(file_name)
+    }
+}
+
+impl Default for OutputFile {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("."),
+            file_name: "output.txt".to_string(),
+        }
+    }
+}
+
+const PLACEHOLDERS: [&str; 4] = ["faction", "list", "date", "time"];
+
+/// Formats a point in time as a `YYYY-MM-DD` date and a `HH-MM-SS` time of day in UTC. The time
+/// uses dashes since colons can't be in file names on Windows.
+fn timestamp(time: SystemTime) -> (String, String) {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+
+    // Turns days since 1970-01-01 into a date in the proleptic Gregorian calendar, counting in
+    // 400-year eras that start on March 1st so that leap days fall at the end of a year.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    (
+        format!("{year:04}-{month:02}-{day:02}"),
+        format!(
+            "{:02}-{:02}-{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        ),
+    )
+}
+
+#[derive(Debug)]
+pub enum OutputFileError {
+    Empty,
+    Separator,
+    UnknownPlaceholder(String),
+    Unclosed(String),
+}
+
+impl fmt::Display for OutputFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the output file name is empty"),
+            Self::Separator => write!(
+                f,
+                "the output file name can't contain a path, set `directory` for that"
+            ),
+            Self::UnknownPlaceholder(name) => write!(
+                f,
+                "unknown placeholder `{{{name}}}` in the output file name, expected one of \
+                 {{faction}}, {{list}}, {{date}} or {{time}}"
+            ),
+            Self::Unclosed(name) => write!(f, "placeholder `{{{name}` is never closed"),
+        }
+    }
+}
+
+impl std::error::Error for OutputFileError {}
@@ -0,0 +1,201 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs,
+    io,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use toml::Spanned;
+
+use crate::{
+    catalogue::{
+        line_of,
+        Catalogue,
+        Faction,
+        Item,
+    },
+    todolist::{
+        Priority,
+        TodolistEntry,
+    },
+};
+
+/// Where target stock profiles are kept, relative to the working directory.
+const PROFILES_PATH: &str = "profiles.toml";
+
+/// How many crates of each item a stockpile should hold, like "keep 9 crates of Loughcasters and
+/// 15 of 7.62".
+pub struct Profile<'a> {
+    pub name: String,
+    pub faction: Option<Faction>,
+    pub targets: Vec<(&'a Item, u32)>,
+}
+
+impl<'a> Profile<'a> {
+    /// Loads `profiles.toml` from the working directory, if there is one.
+    pub fn load_all(catalogue: &'a Catalogue) -> Result<Vec<Self>, ProfileError> {
+        match fs::read_to_string(PROFILES_PATH) {
+            Ok(source) => Self::parse_all(&source, PROFILES_PATH, catalogue),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => Err(ProfileError::Io {
+                path: PROFILES_PATH.to_string(),
+                err,
+            }),
+        }
+    }
+
+    pub fn parse_all(
+        source: &str,
+        path: &str,
+        catalogue: &'a Catalogue,
+    ) -> Result<Vec<Self>, ProfileError> {
+        let error = |offset: usize, message: String| ProfileError::Invalid {
+            path: path.to_string(),
+            line: line_of(source, offset),
+            message,
+        };
+
+        let raw: RawProfiles = toml::from_str(source).map_err(|err| ProfileError::Parse {
+            path: path.to_string(),
+            err,
+        })?;
+
+        let mut profiles: Vec<Self> = Vec::with_capacity(raw.profile.len());
+        for profile in raw.profile {
+            let name = profile.name.get_ref();
+            if profiles.iter().any(|other| other.name == *name) {
+                return Err(error(
+                    profile.name.start(),
+                    format!("there is already a profile named `{name}`"),
+                ));
+            }
+
+            let mut targets = Vec::with_capacity(profile.crates.len());
+            for (item_name, crates) in &profile.crates {
+                let item = catalogue
+                    .item(item_name)
+                    .or_else(|| catalogue.find_item(item_name))
+                    .ok_or_else(|| {
+                    error(
+                        profile.name.start(),
+                        format!("profile `{name}` has a target for `{item_name}`, which is not in the catalogue"),
+                    )
+                })?;
+                targets.push((item, *crates));
+            }
+
+            profiles.push(Self {
+                name: name.clone(),
+                faction: profile.faction,
+                targets,
+            });
+        }
+        Ok(profiles)
+    }
+
+    /// Writes every profile to `profiles.toml`, replacing what was there before.
+    pub fn save_all(profiles: &[Self]) -> Result<(), ProfileError> {
+        let saved = SavedProfiles {
+            profile: profiles
+                .iter()
+                .map(|profile| SavedProfile {
+                    name: &profile.name,
+                    faction: profile.faction,
+                    crates: profile
+                        .targets
+                        .iter()
+                        .map(|(item, crates)| (item.id.as_str(), *crates))
+                        .collect(),
+                })
+                .collect(),
+        };
+        let source = toml::to_string(&saved).map_err(ProfileError::Serialize)?;
+        fs::write(PROFILES_PATH, source).map_err(|err| ProfileError::Io {
+            path: PROFILES_PATH.to_string(),
+            err,
+        })
+    }
+
+    /// The profile as todo-list entries holding target levels.
+    pub fn entries(&self) -> Vec<TodolistEntry<'a>> {
+        self.targets
+            .iter()
+            .map(|(item, crates)| TodolistEntry {
+                item,
+                count: *crates,
+                priority: Priority::Normal,
+            })
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawProfiles {
+    #[serde(default)]
+    profile: Vec<RawProfile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawProfile {
+    name: Spanned<String>,
+    faction: Option<Faction>,
+    /// Crates to keep in stock by the item's ID. Profiles written before items had IDs use
+    /// their names instead, which still work.
+    #[serde(default)]
+    crates: BTreeMap<String, u32>,
+}
+
+/// The same as `RawProfiles`, for writing the profiles back out.
+#[derive(Serialize)]
+struct SavedProfiles<'a> {
+    profile: Vec<SavedProfile<'a>>,
+}
+
+#[derive(Serialize)]
+struct SavedProfile<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    faction: Option<Faction>,
+    crates: BTreeMap<&'a str, u32>,
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io {
+        path: String,
+        err: io::Error,
+    },
+    Parse {
+        path: String,
+        err: toml::de::Error,
+    },
+    Serialize(toml::ser::Error),
+    Invalid {
+        path: String,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, err } => write!(f, "could not access {path}: {err}"),
+            Self::Parse { path, err } => write!(f, "{path}: {err}"),
+            Self::Serialize(err) => write!(f, "could not write the profiles: {err}"),
+            Self::Invalid {
+                path,
+                line,
+                message,
+            } => write!(f, "{path}:{line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
@@ -0,0 +1,192 @@
+use std::{
+    fmt,
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    catalogue::{
+        Catalogue,
+        Faction,
+        Item,
+    },
+    todolist::{
+        Priority,
+        SortMode,
+    },
+};
+
+/// Where saved todo-lists are kept by default, relative to the working directory.
+pub const LISTS_DIR: &str = "lists";
+/// The save format version this build writes. Version 1 lists, which refer to items by name
+/// rather than ID, can still be opened.
+const SAVE_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedList {
+    pub version: u32,
+    pub name: String,
+    pub faction: Faction,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+    /// Whether the entries are crates to keep in stock rather than queues to order.
+    #[serde(default)]
+    pub target_levels: bool,
+    #[serde(default)]
+    pub sort: SortMode,
+    #[serde(default, rename = "entry")]
+    pub entries: Vec<SavedEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SavedEntry {
+    /// The item's ID in the catalogue, or its name in version 1 lists.
+    pub item: String,
+    /// Queues to order, or crates to keep in stock if the list holds target levels.
+    #[serde(alias = "queues")]
+    pub count: u32,
+    #[serde(default, skip_serializing_if = "Priority::is_normal")]
+    pub priority: Priority,
+}
+
+impl SavedList {
+    pub fn new(
+        name: String,
+        faction: Faction,
+        notes: String,
+        target_levels: bool,
+        sort: SortMode,
+        entries: Vec<SavedEntry>,
+    ) -> Self {
+        Self {
+            version: SAVE_VERSION,
+            name,
+            faction,
+            notes,
+            target_levels,
+            sort,
+            entries,
+        }
+    }
+
+    /// Writes the list to `<name>.toml` in `directory`, replacing any earlier list with the same
+    /// name.
+    pub fn save(&self, directory: &Path) -> Result<PathBuf, SaveError> {
+        let path = list_path(directory, &self.name);
+        fs::create_dir_all(directory).map_err(|err| SaveError::Io {
+            path: path.clone(),
+            err,
+        })?;
+        let source = toml::to_string(self).map_err(SaveError::Serialize)?;
+        fs::write(&path, source).map_err(|err| SaveError::Io {
+            path: path.clone(),
+            err,
+        })?;
+        Ok(path)
+    }
+
+    pub fn load(directory: &Path, name: &str) -> Result<Self, SaveError> {
+        let path = list_path(directory, name);
+        let source = fs::read_to_string(&path).map_err(|err| SaveError::Io {
+            path: path.clone(),
+            err,
+        })?;
+        let list: Self = toml::from_str(&source).map_err(|err| SaveError::Parse {
+            path: path.clone(),
+            err,
+        })?;
+        if !(1..=SAVE_VERSION).contains(&list.version) {
+            return Err(SaveError::Version {
+                path,
+                version: list.version,
+            });
+        }
+        Ok(list)
+    }
+
+    /// Looks up an entry's item, which older lists name instead of giving its ID.
+    pub fn find_item<'a>(&self, catalogue: &'a Catalogue, item: &str) -> Option<&'a Item> {
+        if self.version == 1 {
+            catalogue.find_item(item)
+        } else {
+            catalogue.item(item)
+        }
+    }
+}
+
+/// Returns the names of all lists saved in `directory`, sorted alphabetically.
+pub fn saved_lists(directory: &Path) -> io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(names),
+        Err(err) => return Err(err),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+        {
+            if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+                names.push(stem.to_string());
+            }
+        }
+    }
+    names.sort_by_key(|name| name.to_lowercase());
+    Ok(names)
+}
+
+fn list_path(directory: &Path, name: &str) -> PathBuf {
+    directory.join(format!("{}.toml", sanitize_file_name(name)))
+}
+
+/// Replaces anything in a list name that can't go in a file name on every platform.
+pub fn sanitize_file_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io { path: PathBuf, err: io::Error },
+    Parse { path: PathBuf, err: toml::de::Error },
+    Serialize(toml::ser::Error),
+    Version { path: PathBuf, version: u32 },
+    UnknownItem(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, err } => write!(f, "{}: {err}", path.display()),
+            Self::Parse { path, err } => write!(f, "{}: {err}", path.display()),
+            Self::Serialize(err) => write!(f, "could not serialize list: {err}"),
+            Self::Version { path, version } => write!(
+                f,
+                "{}: list version {version} is not supported, expected version {SAVE_VERSION}",
+                path.display()
+            ),
+            Self::UnknownItem(name) => write!(f, "the catalogue has no item named `{name}`"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
@@ -0,0 +1,246 @@
+use std::fmt::{
+    self,
+    Write,
+};
+
+use crate::{
+    catalogue::{
+        Catalogue,
+        Cost,
+    },
+    material::Material,
+    output::{
+        format_material_amounts,
+        format_queue_count,
+    },
+    todolist::TodolistEntry,
+};
+
+/// A user-defined way of writing out the todo-list, one line per entry.
+///
+/// Templates are plain text with placeholders in braces, like `{queues}x {short_name}`. Literal
+/// braces are written as `{{` and `}}`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Template {
+    pub name: String,
+    entry: Vec<Segment>,
+    total: Option<Vec<Segment>>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Placeholder {
+    /// The entry's position in the list, starting at 1.
+    Index,
+    /// The entry's position as a regional indicator emoji.
+    Letter,
+    Name,
+    /// The short name if the item has one, and the full name otherwise.
+    ShortName,
+    Queues,
+    /// The queue count with "Queue" or "Queues" after it.
+    QueuesText,
+    /// How many of the item the queues produce in total.
+    Units,
+    /// One material's amount, written as the material's key like `{bmats}`.
+    Material(Material),
+    /// How many crates of one material are needed, written like `{bmats_crates}`.
+    Crates(Material),
+    /// All materials with their crate counts, the way the built-in format writes them.
+    Materials,
+    Category,
+    Faction,
+    Priority,
+}
+
+impl Placeholder {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "index" => Self::Index,
+            "letter" => Self::Letter,
+            "name" => Self::Name,
+            "short_name" => Self::ShortName,
+            "queues" => Self::Queues,
+            "queues_text" => Self::QueuesText,
+            "units" => Self::Units,
+            "materials" => Self::Materials,
+            "category" => Self::Category,
+            "faction" => Self::Faction,
+            "priority" => Self::Priority,
+            _ => {
+                let (material, crates) = match name.strip_suffix("_crates") {
+                    Some(material) => (material, true),
+                    None => (name, false),
+                };
+                let material = Material::from_key(material)?;
+                if crates {
+                    Self::Crates(material)
+                } else {
+                    Self::Material(material)
+                }
+            }
+        })
+    }
+
+    /// Whether the placeholder makes sense for the list as a whole, rather than a single entry.
+    fn is_total(self) -> bool {
+        matches!(self, Self::Material(_) | Self::Crates(_) | Self::Materials)
+    }
+}
+
+impl Template {
+    /// The built-in format that's used when nothing else has been picked.
+    pub fn builtin() -> Self {
+        Self::parse(
+            "Default",
+            "{letter}・{queues_text} of {short_name}・{materials}",
+            Some("\nTotal・{materials}"),
+        )
+        .expect("the built-in template should always be valid")
+    }
+
+    pub fn parse(name: &str, entry: &str, total: Option<&str>) -> Result<Self, TemplateError> {
+        Ok(Self {
+            name: name.to_string(),
+            entry: parse_segments(entry, false)?,
+            total: total.map(|total| parse_segments(total, true)).transpose()?,
+        })
+    }
+
+    pub fn render_entry(
+        &self,
+        entry: &TodolistEntry,
+        n: usize,
+        catalogue: &Catalogue,
+        letter_width_hack: bool,
+    ) -> String {
+        let mut out = String::new();
+        for segment in &self.entry {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Placeholder(placeholder) => match placeholder {
+                    Placeholder::Index => write!(out, "{}", n + 1).unwrap(),
+                    Placeholder::Letter => {
+                        out.push(char::from_u32(0x1F1E6 + n as u32).unwrap_or('X'));
+                        if letter_width_hack {
+                            out.push(' ');
+                        }
+                    }
+                    Placeholder::Name => out.push_str(&entry.item.name),
+                    Placeholder::ShortName => out.push_str(entry.item.display_name()),
+                    Placeholder::Queues => write!(out, "{}", entry.count).unwrap(),
+                    Placeholder::QueuesText => out.push_str(&format_queue_count(entry.count)),
+                    Placeholder::Units => write!(out, "{}", entry.units()).unwrap(),
+                    Placeholder::Category => out.push_str(
+                        catalogue
+                            .category_of(entry.item)
+                            .map_or("", |category| category.name.as_str()),
+                    ),
+                    Placeholder::Priority => out.push_str(entry.priority.name()),
+                    Placeholder::Faction => {
+                        if let Some(faction) = entry.item.faction {
+                            write!(out, "{faction:?}").unwrap();
+                        }
+                    }
+                    placeholder => render_cost(&mut out, *placeholder, entry.cost()),
+                },
+            }
+        }
+        out
+    }
+
+    pub fn render_total(&self, total: Cost) -> Option<String> {
+        let segments = self.total.as_ref()?;
+        let mut out = String::new();
+        for segment in segments {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Placeholder(placeholder) => render_cost(&mut out, *placeholder, total),
+            }
+        }
+        Some(out)
+    }
+}
+
+fn render_cost(out: &mut String, placeholder: Placeholder, cost: Cost) {
+    match placeholder {
+        Placeholder::Material(material) => write!(out, "{}", cost[material]).unwrap(),
+        Placeholder::Crates(material) => {
+            write!(out, "{}", material.crates(cost[material])).unwrap()
+        }
+        Placeholder::Materials => out.push_str(&format_material_amounts(cost, true)),
+        _ => unreachable!("{placeholder:?} is not a cost placeholder"),
+    }
+}
+
+fn parse_segments(source: &str, total: bool) -> Result<Vec<Segment>, TemplateError> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err(TemplateError::Unclosed(name)),
+                    }
+                }
+                let placeholder = Placeholder::parse(name.trim())
+                    .ok_or_else(|| TemplateError::UnknownPlaceholder(name.clone()))?;
+                if total && !placeholder.is_total() {
+                    return Err(TemplateError::NotATotal(name));
+                }
+                if !text.is_empty() {
+                    segments.push(Segment::Text(std::mem::take(&mut text)));
+                }
+                segments.push(Segment::Placeholder(placeholder));
+            }
+            '}' => return Err(TemplateError::UnmatchedBrace),
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    Ok(segments)
+}
+
+#[derive(Debug)]
+pub enum TemplateError {
+    UnknownPlaceholder(String),
+    Unclosed(String),
+    UnmatchedBrace,
+    NotATotal(String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownPlaceholder(name) => write!(f, "unknown placeholder `{{{name}}}`"),
+            Self::Unclosed(name) => write!(f, "placeholder `{{{name}` is never closed"),
+            Self::UnmatchedBrace => write!(f, "unmatched `}}`, write `}}}}` for a literal brace"),
+            Self::NotATotal(name) => write!(
+                f,
+                "the total line can only use material placeholders, not `{{{name}}}`"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
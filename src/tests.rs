@@ -0,0 +1,574 @@
+use std::{
+    collections::VecDeque,
+    ops::Range,
+    path::PathBuf,
+    sync::{
+        Arc,
+        Mutex,
+    },
+    time::Duration,
+};
+
+use crossterm::event::{
+    Event,
+    KeyCode,
+    KeyEvent,
+    KeyModifiers,
+    MouseButton,
+    MouseEvent,
+    MouseEventKind,
+};
+use fstlg::{
+    catalogue::{
+        Catalogue,
+        Faction,
+    },
+    material::Material,
+    save::SavedList,
+};
+use tui::{
+    backend::TestBackend,
+    buffer::{
+        Buffer,
+        Cell,
+    },
+    style::Color,
+    text::Span,
+    Terminal,
+};
+
+use crate::{
+    config::Config,
+    events::EventSource,
+    run_app,
+    App,
+};
+
+/// Events to feed the app one after the other.
+struct Script(VecDeque<Event>);
+
+impl EventSource for Script {
+    fn next_event(&mut self, _timeout: Duration) -> Option<Event> {
+        self.0.pop_front()
+    }
+
+    fn finished(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// What the app last drew.
+struct Screen(Buffer);
+
+impl Screen {
+    /// The text in an area of the screen, one string per row with the trailing spaces left out.
+    fn text(&self, x: u16, y: u16, width: u16, height: u16) -> Vec<String> {
+        (y..y + height)
+            .map(|y| self.row_text(y, x..x + width, |_| true))
+            .collect()
+    }
+
+    /// The text of the rows highlighted as the selection of a list.
+    fn highlighted(&self) -> Vec<String> {
+        let area = self.0.area;
+        (0..area.height)
+            .map(|y| self.row_text(y, 0..area.width, |cell| cell.bg == Color::DarkGray))
+            .filter(|text| !text.is_empty())
+            .collect()
+    }
+
+    /// Joins the symbols of the cells in a row that pass `filter`. Wide symbols like emoji take
+    /// up more than one cell, and the cells they cover are left out.
+    fn row_text(&self, y: u16, columns: Range<u16>, filter: impl Fn(&Cell) -> bool) -> String {
+        let mut text = String::new();
+        let mut covered = 0;
+        for x in columns {
+            if covered > 0 {
+                covered -= 1;
+                continue;
+            }
+            let cell = self.0.get(x, y);
+            covered = Span::raw(cell.symbol.as_str()).width().saturating_sub(1);
+            if filter(cell) {
+                text.push_str(&cell.symbol);
+            }
+        }
+        text.trim_end().to_string()
+    }
+
+    /// The "Add" list, inside its borders.
+    fn add_list(&self) -> Vec<String> {
+        self.text(1, 1, 38, 27)
+    }
+
+    /// The todo-list, inside its borders.
+    fn todolist(&self) -> Vec<String> {
+        self.text(41, 19, 38, 9)
+    }
+
+    fn status(&self) -> String {
+        self.text(0, 29, 80, 1).remove(0)
+    }
+}
+
+/// An app with the built-in catalogue and the default config.
+fn new_app() -> App {
+    let catalogue: &'static Catalogue = Box::leak(Box::new(Catalogue::builtin()));
+    let config: &'static Config = Box::leak(Box::default());
+    App::new(catalogue, config, Vec::new())
+}
+
+/// Runs the app on an 80 by 30 screen, feeding it `events`, and returns the app along with what
+/// it drew after the last one.
+fn run(events: Vec<Event>) -> (App, Screen) {
+    run_with_height(30, events)
+}
+
+/// Like `run`, on a screen 80 columns wide and `height` rows high.
+fn run_with_height(height: u16, events: Vec<Event>) -> (App, Screen) {
+    run_on(new_app(), height, events)
+}
+
+/// Like `run`, with an app that's been set up beforehand.
+fn run_on(mut app: App, height: u16, events: Vec<Event>) -> (App, Screen) {
+    let terminal = Arc::new(Mutex::new(
+        Terminal::new(TestBackend::new(80, height)).unwrap(),
+    ));
+    run_app(terminal.clone(), &mut app, &mut Script(events.into()));
+    let screen = Screen(terminal.lock().unwrap().backend().buffer().clone());
+    (app, screen)
+}
+
+/// A directory of its own for a test to write files to, which is removed again when the test
+/// ends, even if it fails.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("fstlg-test-{}-{name}", std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn key(code: KeyCode) -> Event {
+    Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+fn shift(code: KeyCode) -> Event {
+    Event::Key(KeyEvent::new(code, KeyModifiers::SHIFT))
+}
+
+/// Types out the characters of `text`, one key press each.
+fn type_text(text: &str) -> Vec<Event> {
+    text.chars().map(|c| key(KeyCode::Char(c))).collect()
+}
+
+fn click(column: u16, row: u16) -> Event {
+    Event::Mouse(MouseEvent {
+        kind: MouseEventKind::Down(MouseButton::Left),
+        column,
+        row,
+        modifiers: KeyModifiers::NONE,
+    })
+}
+
+#[test]
+fn moving_down_starts_at_the_first_item_rather_than_a_divider() {
+    let (_, screen) = run(vec![key(KeyCode::Down)]);
+    assert_eq!(
+        screen.add_list()[..3],
+        [
+            "Small Arms",
+            "Booker Storm Rifle Model 838",
+            "Aalto Storm Rifle 24"
+        ]
+    );
+    assert_eq!(screen.highlighted(), ["Booker Storm Rifle Model 838"]);
+}
+
+#[test]
+fn moving_across_a_divider_skips_it() {
+    // 12.7mm is the last of the small arms, right above the heavy arms.
+    let (_, screen) = run(vec![click(5, 25), key(KeyCode::Down)]);
+    assert_eq!(
+        screen.add_list()[24..27],
+        ["12.7mm", "Heavy Arms", "135 Neville Anti-Tank Rifle"]
+    );
+    assert_eq!(screen.highlighted(), ["135 Neville Anti-Tank Rifle"]);
+
+    let (_, screen) = run(vec![click(5, 25), key(KeyCode::Down), key(KeyCode::Up)]);
+    assert_eq!(screen.highlighted(), ["12.7mm"]);
+}
+
+#[test]
+fn moving_up_from_the_first_item_wraps_around_to_the_last() {
+    let (_, screen) = run(vec![key(KeyCode::Down), key(KeyCode::Up)]);
+    assert_eq!(
+        screen.add_list()[23..],
+        [
+            "Shipping Container",
+            "Bunker Base Kit",
+            "BMS - Class 2 Mobile Auto-Crane",
+            "Concrete Mixer",
+        ]
+    );
+    assert_eq!(screen.highlighted(), ["Concrete Mixer"]);
+
+    // The list scrolls back up only as far as the selection.
+    let (_, screen) = run(vec![key(KeyCode::End), key(KeyCode::Down)]);
+    assert_eq!(screen.add_list()[0], "Booker Storm Rifle Model 838");
+    assert_eq!(screen.highlighted(), ["Booker Storm Rifle Model 838"]);
+}
+
+#[test]
+fn added_items_show_up_in_the_todolist_and_the_output() {
+    let (app, screen) = run(vec![
+        key(KeyCode::Down),
+        key(KeyCode::Enter),
+        key(KeyCode::Enter),
+        click(5, 25),
+        key(KeyCode::Enter),
+        key(KeyCode::Right),
+        key(KeyCode::Char('+')),
+    ]);
+    assert_eq!(
+        screen.todolist()[..3],
+        [
+            "🇦 ・3 Queues of Booker Storm Rifle Mod",
+            "🇧 ・1 Queue of 12.7mm・550 Bmats (6📦)",
+            "",
+        ]
+    );
+    assert_eq!(
+        screen.highlighted(),
+        ["🇦 ・3 Queues of Booker Storm Rifle Mod"]
+    );
+    assert_eq!(screen.text(41, 11, 38, 1), ["Bmats: 3262 (33📦)"]);
+    assert_eq!(
+        app.output(),
+        "🇦・3 Queues of Booker Storm Rifle Model 838・2712 Bmats (28📦)\n\
+         🇧・1 Queue of 12.7mm・550 Bmats (6📦)\n\
+         \n\
+         Total・3262 Bmats (33📦)\n"
+    );
+
+    let (app, screen) = run(vec![
+        key(KeyCode::Down),
+        key(KeyCode::Enter),
+        key(KeyCode::Char('t')),
+        key(KeyCode::Char('t')),
+    ]);
+    assert_eq!(screen.status(), "Output format: Plain");
+    assert_eq!(
+        app.output(),
+        "1) 1 Queue of Booker Storm Rifle Model 838 - 904 Bmats (10 crates)\n\
+         Total: 904 Bmats (10 crates)\n"
+    );
+}
+
+#[test]
+fn switching_factions_relists_the_items() {
+    let (_, screen) = run(vec![key(KeyCode::Down), key(KeyCode::Char('f'))]);
+    assert_eq!(screen.text(41, 1, 38, 1), ["Faction: Colonial"]);
+    assert_eq!(
+        screen.add_list()[..3],
+        ["Small Arms", "\"Dusk\" ce.III", "7.92mm"]
+    );
+    assert_eq!(screen.highlighted(), Vec::<String>::new());
+    assert_eq!(screen.status(), "Switched to Colonial items");
+
+    // Clicking the settings switches back.
+    let (_, screen) = run(vec![key(KeyCode::Char('f')), click(50, 3)]);
+    assert_eq!(screen.text(41, 1, 38, 1), ["Faction: Warden"]);
+    assert_eq!(screen.add_list()[1], "Booker Storm Rifle Model 838");
+}
+
+#[test]
+fn switching_factions_keeps_the_todolist_and_the_search() {
+    let mut events = vec![
+        key(KeyCode::Down),
+        key(KeyCode::Enter),
+        key(KeyCode::Char('/')),
+    ];
+    events.extend(type_text("rifle"));
+    // Typing `f` would go into the search, so this clicks the settings instead.
+    events.push(click(50, 3));
+    let (app, screen) = run(events);
+    assert_eq!(
+        screen.text(0, 0, 40, 1),
+        ["┌Add (search: rifle_)──────────────────┐"]
+    );
+    assert_eq!(screen.text(41, 1, 38, 1), ["Faction: Colonial"]);
+    assert_eq!(screen.add_list()[..2], ["Small Arms", "Argenti r.II Rifle"]);
+    assert_eq!(app.todolist.len(), 1);
+    assert_eq!(
+        app.todolist.entries()[0].item.name,
+        "Booker Storm Rifle Model 838"
+    );
+}
+
+#[test]
+fn an_empty_todolist_ignores_entry_actions() {
+    let (app, screen) = run(vec![key(KeyCode::Right)]);
+    assert_eq!(
+        screen.status(),
+        "Enter: remove · +, =: more · -: fewer · Left: add list · u: undo · ?: help"
+    );
+    assert_eq!(app.todolist_selection.selected(), None);
+
+    let (app, screen) = run(vec![
+        key(KeyCode::Right),
+        key(KeyCode::Down),
+        key(KeyCode::Up),
+        key(KeyCode::Home),
+        key(KeyCode::End),
+        key(KeyCode::Enter),
+        key(KeyCode::Delete),
+        key(KeyCode::Char('+')),
+        key(KeyCode::Char('-')),
+        key(KeyCode::Char('!')),
+        shift(KeyCode::Up),
+        click(50, 20),
+        key(KeyCode::Char('x')),
+    ]);
+    assert!(app.todolist.is_empty());
+    assert_eq!(screen.highlighted(), Vec::<String>::new());
+    assert_eq!(screen.status(), "The todo-list is already empty");
+
+    let (_, screen) = run(vec![key(KeyCode::Right), key(KeyCode::Char('u'))]);
+    assert_eq!(screen.status(), "Nothing to undo");
+}
+
+#[test]
+fn removing_the_last_entry_moves_the_selection_up() {
+    let (app, screen) = run(vec![
+        key(KeyCode::Down),
+        key(KeyCode::Enter),
+        key(KeyCode::Down),
+        key(KeyCode::Enter),
+        key(KeyCode::Right),
+        key(KeyCode::End),
+        key(KeyCode::Enter),
+    ]);
+    assert_eq!(app.todolist.len(), 1);
+    assert_eq!(
+        screen.highlighted(),
+        ["🇦 ・1 Queue of Booker Storm Rifle Mode"]
+    );
+
+    let (app, screen) = run(vec![
+        key(KeyCode::Down),
+        key(KeyCode::Enter),
+        key(KeyCode::Right),
+        key(KeyCode::Enter),
+        key(KeyCode::Down),
+    ]);
+    assert!(app.todolist.is_empty());
+    assert_eq!(screen.highlighted(), Vec::<String>::new());
+    assert_eq!(screen.todolist()[0], "");
+}
+
+#[test]
+fn a_search_without_matches_empties_the_add_list() {
+    let mut events = vec![key(KeyCode::Char('/'))];
+    events.extend(type_text("zzzz"));
+    events.extend([key(KeyCode::Down), key(KeyCode::Up), key(KeyCode::Enter)]);
+    let (app, screen) = run(events.clone());
+    assert!(screen.add_list().iter().all(String::is_empty));
+    assert_eq!(
+        screen.text(0, 0, 40, 1),
+        ["┌Add (search: zzzz_)───────────────────┐"]
+    );
+    assert!(app.todolist.is_empty());
+
+    events.push(key(KeyCode::Esc));
+    let (_, screen) = run(events);
+    assert_eq!(
+        screen.add_list()[..2],
+        ["Small Arms", "Booker Storm Rifle Model 838"]
+    );
+}
+
+#[test]
+fn double_clicking_adds_and_removes_items() {
+    let (app, screen) = run(vec![click(5, 2), click(5, 2)]);
+    assert_eq!(app.todolist.len(), 1);
+    assert_eq!(screen.highlighted(), ["Booker Storm Rifle Model 838"]);
+
+    // Clicking below the last entry doesn't select anything.
+    let (app, screen) = run(vec![click(5, 2), click(5, 2), click(50, 21)]);
+    assert_eq!(app.todolist_selection.selected(), None);
+    assert_eq!(screen.highlighted(), ["Booker Storm Rifle Model 838"]);
+
+    let (app, _) = run(vec![click(5, 2), click(5, 2), click(50, 19), click(50, 19)]);
+    assert!(app.todolist.is_empty());
+}
+
+#[test]
+fn undoing_opening_a_list_brings_back_the_list_it_replaced() {
+    let directory = TempDir::new("lists");
+    let mut app = new_app();
+    app.lists_directory = directory.0.clone();
+
+    let mut events = vec![
+        key(KeyCode::Down),
+        key(KeyCode::Enter),
+        key(KeyCode::Char('s')),
+    ];
+    events.extend(type_text("first"));
+    events.extend([
+        key(KeyCode::Enter),
+        key(KeyCode::Char('x')),
+        key(KeyCode::Down),
+        key(KeyCode::Enter),
+        key(KeyCode::Char('s')),
+        key(KeyCode::Backspace),
+        key(KeyCode::Backspace),
+        key(KeyCode::Backspace),
+        key(KeyCode::Backspace),
+        key(KeyCode::Backspace),
+    ]);
+    events.extend(type_text("second"));
+    events.extend([
+        key(KeyCode::Enter),
+        // Opens "first", the first of the saved lists.
+        key(KeyCode::Char('o')),
+        key(KeyCode::Enter),
+        key(KeyCode::Char('u')),
+        key(KeyCode::Char('s')),
+        key(KeyCode::Enter),
+    ]);
+    let (app, screen) = run_on(app, 30, events);
+    assert_eq!(app.list_name.as_deref(), Some("second"));
+    assert_eq!(screen.text(41, 3, 38, 1), ["List: second"]);
+
+    let first = SavedList::load(&directory.0, "first").unwrap();
+    let items: Vec<&str> = first
+        .entries
+        .iter()
+        .map(|entry| entry.item.as_str())
+        .collect();
+    assert_eq!(items, ["booker_storm_rifle_model_838"]);
+    let second = SavedList::load(&directory.0, "second").unwrap();
+    let items: Vec<&str> = second
+        .entries
+        .iter()
+        .map(|entry| entry.item.as_str())
+        .collect();
+    assert_eq!(items, ["aalto_storm_rifle_24"]);
+}
+
+#[test]
+fn clicking_a_search_result_after_scrolling_adds_it() {
+    // The list was scrolled to the end before searching, and the search starts it over from the
+    // top.
+    let mut events = vec![key(KeyCode::End), key(KeyCode::Char('/'))];
+    events.extend(type_text("booker"));
+    events.extend([click(5, 2), click(5, 2)]);
+    let (app, screen) = run(events);
+    assert_eq!(
+        screen.add_list()[..2],
+        ["Small Arms", "Booker Storm Rifle Model 838"]
+    );
+    assert_eq!(app.todolist.len(), 1);
+    assert_eq!(
+        app.todolist.entries()[0].item.name,
+        "Booker Storm Rifle Model 838"
+    );
+}
+
+#[test]
+fn entries_are_lettered_the_same_on_screen_as_in_the_output() {
+    let (app, screen) = run(vec![
+        key(KeyCode::Down),
+        key(KeyCode::Enter),
+        key(KeyCode::Down),
+        key(KeyCode::Enter),
+        key(KeyCode::Right),
+        key(KeyCode::End),
+        key(KeyCode::Char('!')),
+    ]);
+    let urgent = &app.todolist.entries()[0].item.name;
+    assert_eq!(urgent, "Aalto Storm Rifle 24");
+    assert_eq!(app.todolist_selection.selected(), Some(0));
+    assert!(screen.todolist()[0].starts_with("[Urgent] 🇦"));
+    assert!(app
+        .output()
+        .lines()
+        .any(|line| line.starts_with("🇦") && line.contains(urgent.as_str())));
+}
+
+#[test]
+fn queue_costs_match_what_the_mpf_charges() {
+    let catalogue = Catalogue::builtin();
+    let queue_cost = |id: &str| -> Vec<(Material, u32)> {
+        catalogue.item(id).unwrap().queue_cost.iter().collect()
+    };
+    assert_eq!(queue_cost("no_2_loughcaster"), [(Material::Bmats, 550)]);
+    assert_eq!(queue_cost("the_hangman_757"), [(Material::Bmats, 684)]);
+    assert_eq!(
+        queue_cost("booker_storm_rifle_model_838"),
+        [(Material::Bmats, 904)]
+    );
+    assert_eq!(queue_cost("malone_mk_2"), [(Material::Rmats, 134)]);
+    assert_eq!(
+        queue_cost("150mm"),
+        [(Material::Bmats, 660), (Material::Hemats, 55)]
+    );
+}
+
+#[test]
+fn undo_only_reverts_the_last_change() {
+    let mut events = vec![
+        key(KeyCode::Down),
+        key(KeyCode::Enter),
+        key(KeyCode::Char('n')),
+    ];
+    events.extend(type_text("Hold the line"));
+    events.extend([
+        key(KeyCode::Enter),
+        key(KeyCode::Char('f')),
+        key(KeyCode::Char('l')),
+        key(KeyCode::Char('u')),
+    ]);
+    let (app, screen) = run(events.clone());
+    assert_eq!(screen.status(), "Undone: switched to target levels");
+    assert!(!app.todolist.target_levels);
+    assert_eq!(app.faction, Faction::Colonial);
+    assert_eq!(app.notes, "Hold the line");
+    assert_eq!(app.todolist.len(), 1);
+
+    events.extend([
+        key(KeyCode::Char('u')),
+        key(KeyCode::Char('u')),
+        key(KeyCode::Char('u')),
+    ]);
+    let (app, screen) = run(events);
+    assert_eq!(
+        screen.status(),
+        "Undone: added Booker Storm Rifle Model 838"
+    );
+    assert_eq!(app.faction, Faction::Warden);
+    assert_eq!(app.notes, "");
+    assert!(app.todolist.is_empty());
+}
+
+#[test]
+fn the_todolist_keeps_its_rows_on_a_small_screen() {
+    let events = (0..8)
+        .flat_map(|_| [key(KeyCode::Down), key(KeyCode::Enter)])
+        .collect();
+    let (app, screen) = run_with_height(24, events);
+    assert_eq!(app.todolist.len(), 8);
+    let todolist = screen.text(41, 14, 38, 8);
+    assert!(todolist[0].starts_with("🇦"));
+    assert!(todolist[7].starts_with("🇭"));
+    assert_eq!(screen.text(41, 1, 38, 1), ["Faction: Warden"]);
+}
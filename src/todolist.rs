@@ -0,0 +1,225 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    catalogue::{
+        Cost,
+        Item,
+    },
+    inventory::{
+        shortfall,
+        Inventory,
+    },
+};
+
+/// What to order at the MPF, independent of how it's shown.
+#[derive(Clone, Default)]
+pub struct Todolist<'a> {
+    entries: Vec<TodolistEntry<'a>>,
+    /// Whether the entries hold how many crates to keep in stock, rather than how many queues
+    /// to order. What gets ordered is then the shortfall, see `Todolist::order`.
+    pub target_levels: bool,
+    sort_mode: SortMode,
+}
+
+#[derive(Clone)]
+pub struct TodolistEntry<'a> {
+    pub item: &'a Item,
+    /// How many queues to order, or in target level mode, how many crates to keep in stock.
+    pub count: u32,
+    pub priority: Priority,
+}
+
+/// How badly an entry is needed. The todo-list and its output are grouped by priority, most
+/// urgent first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    Urgent,
+    #[default]
+    Normal,
+    /// Only if there are materials to spare after the rest of the list.
+    IfSpare,
+}
+
+/// How the todo-list is ordered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortMode {
+    /// In the order of the catalogue, which new entries are sorted into.
+    #[default]
+    Category,
+    /// In whatever order the user moved the entries into. New entries go at the end.
+    Manual,
+}
+
+impl<'a> Todolist<'a> {
+    pub fn new(entries: Vec<TodolistEntry<'a>>, target_levels: bool, sort_mode: SortMode) -> Self {
+        let mut todolist = Self {
+            entries,
+            target_levels,
+            sort_mode,
+        };
+        todolist.sort();
+        todolist
+    }
+
+    pub fn entries(&self) -> &[TodolistEntry<'a>] {
+        &self.entries
+    }
+
+    pub fn get(&self, index: usize) -> Option<&TodolistEntry<'a>> {
+        self.entries.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn sort_mode(&self) -> SortMode {
+        self.sort_mode
+    }
+
+    /// Switches how the entries are ordered. Going back to sorting by category sorts them right
+    /// away.
+    pub fn set_sort_mode(&mut self, sort_mode: SortMode) {
+        self.sort_mode = sort_mode;
+        self.sort();
+    }
+
+    pub fn position(&self, item: &Item) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|entry| entry.item.id == item.id)
+    }
+
+    /// Adds to the item's entry, or adds an entry for it if there's none yet. Returns where the
+    /// entry ended up.
+    pub fn add(&mut self, item: &'a Item, count: u32) -> usize {
+        match self.position(item) {
+            Some(position) => {
+                let entry = &mut self.entries[position];
+                entry.count = entry.count.saturating_add(count);
+                position
+            }
+            None => {
+                self.entries.push(TodolistEntry {
+                    item,
+                    count,
+                    priority: Priority::Normal,
+                });
+                self.sort();
+                self.position(item)
+                    .expect("the entry should still be there after sorting")
+            }
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) -> TodolistEntry<'a> {
+        self.entries.remove(index)
+    }
+
+    pub fn set_count(&mut self, index: usize, count: u32) {
+        self.entries[index].count = count;
+    }
+
+    /// Changes an entry's priority, which moves it into that priority's group. Returns where the
+    /// entry ended up.
+    pub fn set_priority(&mut self, index: usize, priority: Priority) -> usize {
+        self.entries[index].priority = priority;
+        let item = self.entries[index].item;
+        self.sort();
+        self.position(item)
+            .expect("the entry should still be there after sorting")
+    }
+
+    /// Swaps an entry with the one above or below it, which switches to ordering by hand.
+    /// Returns where the entry ended up, or `None` if it's already at that end of its priority's
+    /// group.
+    pub fn move_entry(&mut self, index: usize, down: bool) -> Option<usize> {
+        let target = if down {
+            index + 1
+        } else {
+            index.checked_sub(1)?
+        };
+        if self.entries.get(target)?.priority != self.entries[index].priority {
+            return None;
+        }
+        self.entries.swap(index, target);
+        self.sort_mode = SortMode::Manual;
+        Some(target)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// What to order at the MPF. These are the entries themselves, unless they hold target
+    /// levels, in which case it's whatever the inventory is short of.
+    pub fn order(&self, inventory: Option<&Inventory>) -> Vec<TodolistEntry<'a>> {
+        if self.target_levels {
+            shortfall(&self.entries, inventory)
+        } else {
+            self.entries.clone()
+        }
+    }
+
+    pub fn total_cost(&self, inventory: Option<&Inventory>) -> Cost {
+        self.order(inventory).iter().map(TodolistEntry::cost).sum()
+    }
+
+    /// Keeps the entries grouped by priority, in the same order as the output, so that entries
+    /// are numbered the same on screen as in the output.
+    fn sort(&mut self) {
+        match self.sort_mode {
+            SortMode::Category => sort_entries(&mut self.entries),
+            SortMode::Manual => self.entries.sort_by_key(|entry| entry.priority),
+        }
+    }
+}
+
+impl TodolistEntry<'_> {
+    pub fn cost(&self) -> Cost {
+        self.item.queue_cost * self.count
+    }
+
+    pub fn units(&self) -> u32 {
+        self.item.units_per_queue().saturating_mul(self.count)
+    }
+}
+
+impl Priority {
+    pub const ALL: [Self; 3] = [Self::Urgent, Self::Normal, Self::IfSpare];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Urgent => "Urgent",
+            Self::Normal => "Normal",
+            Self::IfSpare => "If spare mats",
+        }
+    }
+
+    pub fn is_normal(&self) -> bool {
+        *self == Self::Normal
+    }
+
+    /// The priority after this one, going from normal to urgent to "if spare mats".
+    pub fn next(self) -> Self {
+        match self {
+            Self::Normal => Self::Urgent,
+            Self::Urgent => Self::IfSpare,
+            Self::IfSpare => Self::Normal,
+        }
+    }
+}
+
+/// Sorts entries by priority, and then in the order their items appear in the catalogue.
+pub fn sort_entries(entries: &mut [TodolistEntry]) {
+    entries.sort_by_key(|entry| (entry.priority, entry.item.category, entry.item.position));
+}